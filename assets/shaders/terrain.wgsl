@@ -291,10 +291,27 @@ fn fire(vo: vec2<f32>) -> vec3<f32> {
 }
 
 
+// while rewinding the terrain is drained of color and rolls like an old tape
+fn rewind_effect(c: vec4f, p: vec2f) -> vec4f {
+    let grey = dot(c.rgb, vec3f(0.299, 0.587, 0.114));
+    let lines = 0.85 + 0.15 * sin(p.y * 0.8 + global_time.v.x * 40.0);
+    return vec4f(mix(c.rgb, grey * vec3f(0.8, 0.9, 1.2), 0.8) * lines, c.a);
+}
+
 @fragment
 fn fragment(
     mesh: VertexOutput,
 ) -> @location(0) vec4<f32> {
+    let c = terrain_color(mesh);
+    if level.v.z == 1 {
+        return rewind_effect(c, mesh.world_position.xy);
+    }
+    return c;
+}
+
+fn terrain_color(
+    mesh: VertexOutput,
+) -> vec4<f32> {
     let distance_to_player = (length(player_position.v.xy - mesh.world_position.xy));
 
     let f1 = player_position.v.zw;
@@ -309,6 +326,22 @@ fn fragment(
     let f = min(f3_d, min(f1_d, f2_d));
     let f_d = 1.0 - smoothstep(0.0, 1280.0, f);
 
+    // heatmap debug mode, global_time.y/z hold the range of the time field
+    if level.v.y == 1 {
+        let t = textureSample(time_texture, time_texture_sampler, mesh.uv.yx).r;
+        let range = max(global_time.v.z - global_time.v.y, 0.0001);
+        let ramp = color(vec3f(0.5), vec3f(0.5), vec3f(1.0), vec3f(0.0, 0.33, 0.67), 0.7 * (t - global_time.v.y) / range);
+        let solid = textureSample(height_texture, height_texture_sampler, mesh.uv.yx).r > 0.5;
+        let k = textureSample(kill_texture, kill_texture_sampler, mesh.uv.yx).r > 0.5;
+        if k {
+            return vec4f(mix(ramp, vec3f(0.7, 0.24, 0.33), 0.6), 1.0);
+        }
+        if solid {
+            return vec4f(ramp * 0.35, 1.0);
+        }
+        return vec4f(ramp, 1.0);
+    }
+
     if distance_to_player > 350.0 {
         return vec4f(0.0, 0.0, 0.0, 1.0);
     }
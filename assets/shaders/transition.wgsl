@@ -0,0 +1,68 @@
+#import bevy_ui::ui_vertex_output::UiVertexOutput
+
+struct TransitionParams {
+    v: vec4f,
+}
+
+// x: how far the screen is covered (0-1), y: the style, zw: where the player is in UV
+@group(1) @binding(0) var<uniform> params: TransitionParams;
+@group(1) @binding(1) var<uniform> color: vec4f;
+
+const VIGNETTE: i32 = 0;
+const FADE: i32 = 1;
+const IRIS: i32 = 2;
+const INK: i32 = 3;
+
+// Lines of ink across the screen, as many as the poem shows at once.
+const INK_LINES: f32 = 24.0;
+
+fn hash(x: f32) -> f32 {
+    return fract(sin(x * 127.1) * 43758.5453);
+}
+
+// Darkness closing in from the edges of the screen towards the player.
+fn vignette(uv: vec2f, aspect: f32, center: vec2f, covered: f32) -> f32 {
+    let d = length((uv - center) * vec2(aspect, 1.0));
+    // far enough out that nothing is covered at 0, every corner is reached at 1
+    let radius = mix(1.2 + aspect, -0.3, covered);
+    return smoothstep(radius, radius + 0.3, d);
+}
+
+// A sharp circle around the player, shrinking until it closes.
+fn iris(uv: vec2f, aspect: f32, center: vec2f, covered: f32, pixel: f32) -> f32 {
+    let scale = vec2(aspect, 1.0);
+    let d = length((uv - center) * scale);
+    // the farthest corner, so the circle starts outside the screen
+    let farthest = length(max(center, 1.0 - center) * scale);
+    // closes completely at 1, without a dot left in the middle
+    let radius = (farthest + pixel) * (1.0 - covered) - pixel;
+    return smoothstep(radius - pixel, radius + pixel, d);
+}
+
+// Every line is written from the left, some ahead of others, with a ragged wet edge.
+fn ink(uv: vec2f, covered: f32) -> f32 {
+    let line = floor(uv.y * INK_LINES);
+    let head_start = hash(line) * 0.3;
+    let edge = covered * 1.6 - 0.3 + head_start - 0.15;
+    let ragged = (hash(line + floor(uv.x * 40.0)) - 0.5) * 0.03;
+    return 1.0 - smoothstep(edge - 0.02, edge + 0.02, uv.x + ragged);
+}
+
+@fragment
+fn fragment(in: UiVertexOutput) -> @location(0) vec4f {
+    let covered = params.v.x;
+    let style = i32(params.v.y);
+    let center = params.v.zw;
+    let aspect = in.size.x / in.size.y;
+
+    var alpha = covered;
+    if style == VIGNETTE {
+        alpha = vignette(in.uv, aspect, center, covered);
+    } else if style == IRIS {
+        alpha = iris(in.uv, aspect, center, covered, 1.0 / in.size.y);
+    } else if style == INK {
+        alpha = ink(in.uv, covered);
+    }
+    // FADE covers the screen evenly
+    return vec4(color.rgb, color.a * alpha);
+}
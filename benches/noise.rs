@@ -0,0 +1,30 @@
+use std::hint::black_box;
+
+use bevy::math::Vec3;
+use criterion::{Criterion, criterion_group, criterion_main};
+use dornburg::terrain::{dotnoise, fbm};
+
+fn noise(c: &mut Criterion) {
+    let point = Vec3::new(0.3, 0.7, 12.5);
+    c.bench_function("dotnoise", |b| b.iter(|| dotnoise(black_box(point))));
+    // the parameters all update_levelN use
+    c.bench_function("fbm", |b| {
+        b.iter(|| fbm(black_box(point), 5, 20.0, 1.2, 0.6))
+    });
+    // one noise sample per voxel, as in a generation without protected voxels
+    c.bench_function("fbm/128x128", |b| {
+        b.iter(|| {
+            let mut total = 0.0;
+            for x in 0..128 {
+                for y in 0..128 {
+                    let p = Vec3::new(x as f32 / 128.0, y as f32 / 128.0, 12.5);
+                    total += fbm(black_box(p), 5, 20.0, 1.2, 0.6);
+                }
+            }
+            total
+        })
+    });
+}
+
+criterion_group!(benches, noise);
+criterion_main!(benches);
@@ -0,0 +1,206 @@
+use std::{hint::black_box, path::Path};
+
+use bevy::{
+    math::Vec2,
+    tasks::{TaskPool, TaskPoolBuilder},
+};
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use dornburg::{
+    layout::{LevelLayout, load_level_image},
+    terrain::{
+        LevelMemory, NeighborCounts, TimeDiluationMap, TimeFieldParams, VoxelizedView, cell_center,
+        evolve,
+    },
+};
+
+/// The levels of the game, in the order of [`dornburg::RequiredAssets::levels`].
+fn levels() -> Vec<LevelLayout> {
+    (1..=4)
+        .map(|i| {
+            let path =
+                Path::new(env!("CARGO_MANIFEST_DIR")).join(format!("assets/levels/level_{i}.png"));
+            let image = load_level_image(&path).unwrap();
+            LevelLayout::parse(&image).unwrap()
+        })
+        .collect()
+}
+
+/// The time field after the player stood at `player` for `seconds`.
+fn time_field(player: Vec2, seconds: f32) -> TimeDiluationMap {
+    let mut time = TimeDiluationMap::zero();
+    for _ in 0..(seconds * 60.0) as u32 {
+        time.advance(player, 1.0 / 60.0);
+    }
+    time
+}
+
+/// xorshift, so the grids are the same on every run.
+fn random_columns(size: usize, seed: u64) -> Vec<u128> {
+    let mut state = seed;
+    let mut next = || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    (0..size * size / 128)
+        .map(|_| ((next() as u128) << 64) | next() as u128)
+        .collect()
+}
+
+/// The per cell lookup the terrain used before the bit planes, for grids of any size.
+fn naive_counts(columns: &[u128], size: usize) -> Vec<u8> {
+    let words = size / 128;
+    let get = |x: i32, y: i32| {
+        let x = x.clamp(0, size as i32 - 1) as usize;
+        let y = y.clamp(0, size as i32 - 1) as usize;
+        columns[x * words + y / 128] & (1 << (y % 128)) > 0
+    };
+    let mut counts = Vec::with_capacity(size * size);
+    for x in 0..size as i32 {
+        for y in 0..size as i32 {
+            let mut s = 0;
+            for x_o in -1..=1 {
+                for y_o in -1..=1 {
+                    s += get(x + x_o, y + y_o) as u8;
+                }
+            }
+            counts.push(s);
+        }
+    }
+    counts
+}
+
+fn neighbor_counts(c: &mut Criterion) {
+    let mut group = c.benchmark_group("neighbor_counts");
+    for size in [128, 512] {
+        let columns = random_columns(size, 0x9E37_79B9_7F4A_7C15);
+        group.bench_with_input(BenchmarkId::new("lookup", size), &columns, |b, columns| {
+            b.iter(|| naive_counts(black_box(columns), size))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("bitboard", size),
+            &columns,
+            |b, columns| b.iter(|| NeighborCounts::new(black_box(columns), size / 128)),
+        );
+    }
+
+    let mut voxels = VoxelizedView::empty();
+    let columns = random_columns(128, 42);
+    for x in 0..128 {
+        for y in 0..128 {
+            voxels.set(x, y, columns[x as usize] & (1 << y) > 0);
+        }
+    }
+    group.bench_function("get_surrounding/128", |b| {
+        b.iter(|| {
+            let mut total = 0u32;
+            for x in 0..128 {
+                for y in 0..128 {
+                    total += black_box(&voxels).get_surrounding(x, y, 1) as u32;
+                }
+            }
+            total
+        })
+    });
+    group.finish();
+}
+
+/// The time field on a single thread and on all threads of the machine.
+fn advance_time_field(c: &mut Criterion) {
+    let mut group = c.benchmark_group("advance_time");
+    let single = TaskPoolBuilder::new().num_threads(1).build();
+    let all = TaskPool::new();
+    for size in [128u32, 512] {
+        let player = Vec2::new(100.0, -40.0);
+        let params = TimeFieldParams::for_level(0);
+        let mut serial = TimeDiluationMap::with_size(size, params);
+        let mut parallel = TimeDiluationMap::with_size(size, params);
+        for _ in 0..10 {
+            serial.advance_on(&single, player, 1.0 / 60.0);
+            parallel.advance_on(&all, player, 1.0 / 60.0);
+        }
+        for x in 0..size {
+            for y in 0..size {
+                assert_eq!(serial.get(x, y), parallel.get(x, y));
+            }
+        }
+
+        group.bench_function(BenchmarkId::new("serial", size), |b| {
+            b.iter(|| serial.advance_on(&single, black_box(player), 1.0 / 60.0))
+        });
+        group.bench_function(BenchmarkId::new("task_pool", size), |b| {
+            b.iter(|| parallel.advance_on(&all, black_box(player), 1.0 / 60.0))
+        });
+    }
+    group.finish();
+}
+
+/// What `update_time` does every frame.
+fn update_time(c: &mut Criterion) {
+    let player = Vec2::new(100.0, -40.0);
+    let mut time = time_field(player, 10.0);
+    c.bench_function("update_time", |b| {
+        b.iter(|| time.advance(black_box(player), 1.0 / 60.0))
+    });
+}
+
+/// One generation of every level, with the player standing at the spawn for 30 seconds.
+fn generation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("generation");
+    for (level, layout) in levels().into_iter().enumerate() {
+        let (x, y) = layout.spawn().unwrap();
+        let player = cell_center(bevy::math::UVec2::new(x, y));
+        let time = time_field(player, 30.0);
+        let memory = LevelMemory::for_level(&layout.voxels, level as u32);
+        group.bench_function(BenchmarkId::new("update_level", level + 1), |b| {
+            b.iter(|| {
+                evolve(
+                    black_box(&layout.voxels),
+                    &time,
+                    &memory,
+                    level as u32,
+                    0,
+                    player,
+                )
+            })
+        });
+    }
+    group.finish();
+}
+
+fn colliders(c: &mut Criterion) {
+    let mut group = c.benchmark_group("collider");
+    for (level, layout) in levels().into_iter().enumerate() {
+        group.bench_function(BenchmarkId::new("voxels", level + 1), |b| {
+            b.iter(|| black_box(&layout.voxels).collider())
+        });
+        group.bench_function(BenchmarkId::new("killzones", level + 1), |b| {
+            b.iter(|| black_box(&layout.killzones).collider())
+        });
+    }
+    group.finish();
+}
+
+fn textures(c: &mut Criterion) {
+    let layout = levels().swap_remove(0);
+    let time = time_field(Vec2::ZERO, 30.0);
+    let mut group = c.benchmark_group("as_tex");
+    group.bench_function("voxels", |b| b.iter(|| black_box(&layout.voxels).as_tex()));
+    group.bench_function("killzones", |b| {
+        b.iter(|| black_box(&layout.killzones).as_tex())
+    });
+    group.bench_function("time", |b| b.iter(|| black_box(&time).as_tex()));
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    neighbor_counts,
+    advance_time_field,
+    update_time,
+    generation,
+    colliders,
+    textures
+);
+criterion_main!(benches);
@@ -0,0 +1,149 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    Opts,
+    death::DeathCounter,
+    levels::{CurrentLevel, LevelScreens, PoemState, spawn_timer},
+    screens::Screen,
+    terrain::{TimeDiluationMap, UpdateTimer, spawn_level},
+};
+
+/// Deaths on a level that earn one step of relief.
+const DEATHS_PER_STEP: u32 = 3;
+/// Seconds spent in a level that earn one step of relief.
+const SECONDS_PER_STEP: f32 = 180.0;
+/// The most relief a level gets.
+const MAX_STEPS: u32 = 4;
+/// Per step, the bubble around the player grows by this fraction.
+const BUBBLE_PER_STEP: f32 = 0.08;
+/// Per step, generations of the terrain are this fraction further apart.
+const INTERVAL_PER_STEP: f32 = 0.15;
+/// Per step, the poem lasts this many seconds longer.
+const POEM_PER_STEP: f32 = 10.0;
+
+/// Eases levels the player keeps failing, enabled with `--adaptive`.
+pub struct AdaptivePlugin;
+
+impl Plugin for AdaptivePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AdaptiveDifficulty>();
+        app.add_systems(OnEnter(Screen::Gameplay), reset_difficulty);
+        app.add_systems(
+            OnEnter(LevelScreens::Level),
+            ease_level
+                .after(spawn_level)
+                .after(spawn_timer)
+                .run_if(|opts: Res<Opts>| opts.adaptive),
+        );
+        app.add_systems(
+            Update,
+            count_time.run_if(in_state(Screen::Gameplay).and(in_state(LevelScreens::Level))),
+        );
+    }
+}
+
+/// How the current run went so far, indexed like [`CurrentLevel`].
+#[derive(Resource, Default)]
+pub struct AdaptiveDifficulty {
+    pub levels: Vec<LevelStats>,
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct LevelStats {
+    /// Deaths on the level when it was last started, see [`DeathCounter`].
+    pub deaths: u32,
+    /// Seconds spent in the level, over all attempts.
+    pub seconds: f32,
+    /// The relief the level was last started with.
+    pub steps: u32,
+}
+
+impl LevelStats {
+    /// The relief earned so far, it only grows during a run.
+    fn earned_steps(&self) -> u32 {
+        let steps = self.deaths / DEATHS_PER_STEP + (self.seconds / SECONDS_PER_STEP) as u32;
+        steps.clamp(self.steps, MAX_STEPS)
+    }
+
+    pub fn bubble_scale(&self) -> f32 {
+        1.0 + BUBBLE_PER_STEP * self.steps as f32
+    }
+
+    pub fn interval_scale(&self) -> f32 {
+        1.0 + INTERVAL_PER_STEP * self.steps as f32
+    }
+
+    pub fn poem_bonus(&self) -> f32 {
+        POEM_PER_STEP * self.steps as f32
+    }
+}
+
+impl AdaptiveDifficulty {
+    pub fn level(&mut self, level: u32) -> &mut LevelStats {
+        let i = level as usize;
+        if self.levels.len() <= i {
+            self.levels.resize(i + 1, LevelStats::default());
+        }
+        &mut self.levels[i]
+    }
+
+    /// One line per level that was eased, empty if none was.
+    pub fn summary(&self) -> String {
+        self.levels
+            .iter()
+            .enumerate()
+            .filter(|(_, stats)| stats.steps > 0)
+            .map(|(i, stats)| {
+                format!(
+                    "Level {}: {} deaths in {:.0}s, eased to a {:.0}% wider bubble, {:.0}% slower terrain and {:.0}s more poem",
+                    i + 1,
+                    stats.deaths,
+                    stats.seconds,
+                    (stats.bubble_scale() - 1.0) * 100.0,
+                    (stats.interval_scale() - 1.0) * 100.0,
+                    stats.poem_bonus(),
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+fn reset_difficulty(mut difficulty: ResMut<AdaptiveDifficulty>) {
+    difficulty.levels.clear();
+}
+
+fn count_time(
+    mut difficulty: ResMut<AdaptiveDifficulty>,
+    current_level: Res<CurrentLevel>,
+    time: Res<Time>,
+) {
+    difficulty.level(current_level.0).seconds += time.delta_secs();
+}
+
+/// Scales the freshly spawned level by the relief earned on it.
+pub fn ease_level(
+    mut difficulty: ResMut<AdaptiveDifficulty>,
+    current_level: Res<CurrentLevel>,
+    deaths: Res<DeathCounter>,
+    mut terrain: Query<(&mut TimeDiluationMap, &mut UpdateTimer)>,
+    mut poem: Query<&mut PoemState>,
+) {
+    let stats = difficulty.level(current_level.0);
+    stats.deaths = deaths.0.get(&current_level.0).copied().unwrap_or_default();
+    stats.steps = stats.earned_steps();
+    if stats.steps == 0 {
+        return;
+    }
+    for (mut time, mut timer) in &mut terrain {
+        time.bubble = time.bubble.scaled(stats.bubble_scale());
+        let interval = timer.0.duration().mul_f32(stats.interval_scale());
+        timer.0.set_duration(interval);
+    }
+    for mut poem in &mut poem {
+        let duration = poem.timer.duration() + Duration::from_secs_f32(stats.poem_bonus());
+        poem.timer.set_duration(duration);
+    }
+}
@@ -0,0 +1,201 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dev::console_closed,
+    layout::ANCHOR_COLOR,
+    levels::LevelScreens,
+    player::PlayerMarker,
+    terrain::{TimeDiluationMap, TimeSource, update_time},
+};
+
+/// Radius of the stasis bubble around a lantern.
+pub const ANCHOR_RADIUS: f32 = 160.0;
+/// Seconds a lantern holds the time around it after it was placed.
+pub const ANCHOR_DURATION: f32 = 20.0;
+/// How close the player has to be to pick up a lantern.
+const PICKUP_DISTANCE: f32 = 40.0;
+
+pub struct AnchorPlugin;
+
+impl Plugin for AnchorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                carry_anchor.run_if(console_closed),
+                follow_carrier,
+                tick_anchors,
+                anchor_sources.before(update_time),
+                show_bubbles,
+            )
+                .chain()
+                .run_if(in_state(LevelScreens::Level)),
+        );
+    }
+}
+
+/// A lantern holding the time around it still, the terrain within `radius` does not change
+/// until `remaining` runs out. Picking it up and placing it again restarts it.
+#[derive(Component)]
+pub struct TimeAnchor {
+    pub radius: f32,
+    pub remaining: Timer,
+}
+
+impl TimeAnchor {
+    pub fn new(radius: f32, duration: f32) -> TimeAnchor {
+        TimeAnchor {
+            radius,
+            remaining: Timer::from_seconds(duration, TimerMode::Once),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.remaining.is_finished()
+    }
+}
+
+/// The lantern the player is holding, it has no effect while carried.
+#[derive(Component)]
+pub struct Carried;
+
+/// A lantern as kept in a savestate.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AnchorState {
+    pub position: Vec2,
+    pub remaining: Timer,
+    pub carried: bool,
+}
+
+impl AnchorState {
+    pub fn capture(transform: &Transform, anchor: &TimeAnchor, carried: bool) -> AnchorState {
+        AnchorState {
+            position: transform.translation.xy(),
+            remaining: anchor.remaining.clone(),
+            carried,
+        }
+    }
+
+    pub fn spawn(
+        &self,
+        commands: &mut Commands,
+        meshes: &mut Assets<Mesh>,
+        colors: &mut Assets<ColorMaterial>,
+    ) {
+        let entity = spawn_anchor(commands, meshes, colors, self.position);
+        let mut anchor = commands.entity(entity);
+        anchor.insert(TimeAnchor {
+            radius: ANCHOR_RADIUS,
+            remaining: self.remaining.clone(),
+        });
+        if self.carried {
+            anchor.insert(Carried);
+        }
+    }
+}
+
+/// The visible extent of the stasis bubble, a child of the lantern.
+#[derive(Component)]
+struct AnchorBubble;
+
+pub fn spawn_anchor(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    colors: &mut Assets<ColorMaterial>,
+    position: Vec2,
+) -> Entity {
+    let color = Srgba::hex(ANCHOR_COLOR).unwrap();
+    commands
+        .spawn((
+            DespawnOnExit(LevelScreens::Level),
+            Mesh2d(meshes.add(Circle::new(7.0))),
+            MeshMaterial2d(colors.add(Color::Srgba(color))),
+            Transform::from_translation(position.extend(0.5)),
+            TimeAnchor::new(ANCHOR_RADIUS, ANCHOR_DURATION),
+            children![(
+                Mesh2d(meshes.add(Circle::new(ANCHOR_RADIUS))),
+                MeshMaterial2d(colors.add(Color::Srgba(color.with_alpha(0.08)))),
+                Transform::from_xyz(0.0, 0.0, -0.1),
+                AnchorBubble,
+            )],
+        ))
+        .id()
+}
+
+/// E picks up the closest lantern or places the carried one.
+fn carry_anchor(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    player: Single<&Transform, With<PlayerMarker>>,
+    mut anchors: Query<(Entity, &Transform, &mut TimeAnchor, Has<Carried>)>,
+) {
+    if !keys.just_pressed(KeyCode::KeyE) {
+        return;
+    }
+    let p = player.translation.xy();
+    if let Some((entity, _, mut anchor, _)) = anchors.iter_mut().find(|a| a.3) {
+        anchor.remaining.reset();
+        commands.entity(entity).remove::<Carried>();
+        return;
+    }
+    let closest = anchors
+        .iter()
+        .map(|(entity, transform, _, _)| (entity, transform.translation.xy().distance(p)))
+        .filter(|(_, distance)| *distance < PICKUP_DISTANCE)
+        .min_by(|a, b| a.1.total_cmp(&b.1));
+    if let Some((entity, _)) = closest {
+        commands.entity(entity).insert(Carried);
+    }
+}
+
+fn follow_carrier(
+    player: Single<&Transform, (With<PlayerMarker>, Without<Carried>)>,
+    mut carried: Query<&mut Transform, With<Carried>>,
+) {
+    for mut transform in &mut carried {
+        transform.translation.x = player.translation.x;
+        transform.translation.y = player.translation.y + 16.0;
+    }
+}
+
+fn tick_anchors(time: Res<Time>, mut anchors: Query<&mut TimeAnchor, Without<Carried>>) {
+    for mut anchor in &mut anchors {
+        anchor.remaining.tick(time.delta());
+    }
+}
+
+/// Every placed lantern is a sink stopping the time field, which also protects it from the CA.
+fn anchor_sources(
+    anchors: Query<(&Transform, &TimeAnchor), Without<Carried>>,
+    mut times: Query<&mut TimeDiluationMap>,
+) {
+    let sources: Vec<TimeSource> = anchors
+        .iter()
+        .filter(|(_, anchor)| anchor.is_active())
+        .map(|(transform, anchor)| TimeSource {
+            position: transform.translation.xy(),
+            radius: anchor.radius,
+            rate: 0.0,
+        })
+        .collect();
+    for mut time in &mut times {
+        time.sources.clone_from(&sources);
+    }
+}
+
+fn show_bubbles(
+    anchors: Query<(&TimeAnchor, Has<Carried>)>,
+    mut bubbles: Query<(&ChildOf, &mut Visibility), With<AnchorBubble>>,
+) {
+    for (parent, mut visibility) in &mut bubbles {
+        if let Ok((anchor, carried)) = anchors.get(parent.parent()) {
+            let shown = anchor.is_active() && !carried;
+            visibility.set_if_neq(if shown {
+                Visibility::Inherited
+            } else {
+                Visibility::Hidden
+            });
+        }
+    }
+}
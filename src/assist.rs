@@ -0,0 +1,160 @@
+use bevy::{
+    feathers::{
+        controls::{ButtonProps, SliderProps, button, checkbox, slider},
+        theme::ThemeBackgroundColor,
+        tokens,
+    },
+    prelude::*,
+    ui::Checked,
+    ui_widgets::{SliderPrecision, SliderStep, SliderValue, ValueChange, observe},
+};
+
+use crate::screens::{Screen, go_to_main};
+
+/// The slowest game speed the slider allows.
+const MIN_GAME_SPEED: f32 = 0.25;
+
+/// The assist menu, reachable from the main menu.
+pub struct AssistPlugin;
+
+impl Plugin for AssistPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Assists>();
+        app.add_systems(OnEnter(Screen::Assist), setup_assist);
+        app.add_systems(OnEnter(Screen::Gameplay), apply_game_speed);
+        app.add_systems(OnExit(Screen::Gameplay), reset_game_speed);
+    }
+}
+
+/// Makes the game easier, runs using any of these are flagged.
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct Assists {
+    /// Relative speed of [`Time<Virtual>`] during gameplay, slowing physics, the poem and the terrain.
+    pub game_speed: f32,
+    /// Lava does not restart the level.
+    pub invincible: bool,
+    /// The poem never runs out.
+    pub infinite_timer: bool,
+}
+
+impl Default for Assists {
+    fn default() -> Assists {
+        Assists {
+            game_speed: 1.0,
+            invincible: false,
+            infinite_timer: false,
+        }
+    }
+}
+
+impl Assists {
+    pub fn any(&self) -> bool {
+        self.game_speed < 1.0 || self.invincible || self.infinite_timer
+    }
+
+    /// The assists in use, for the end screen.
+    pub fn describe(&self) -> String {
+        let mut used = Vec::new();
+        if self.game_speed < 1.0 {
+            used.push(format!("game speed {:.0}%", self.game_speed * 100.0));
+        }
+        if self.invincible {
+            used.push("invincible".to_string());
+        }
+        if self.infinite_timer {
+            used.push("infinite timer".to_string());
+        }
+        used.join(", ")
+    }
+}
+
+fn apply_game_speed(assists: Res<Assists>, mut time: ResMut<Time<Virtual>>) {
+    time.set_relative_speed(assists.game_speed);
+}
+
+fn reset_game_speed(mut time: ResMut<Time<Virtual>>) {
+    time.set_relative_speed(1.0);
+}
+
+fn setup_assist(mut commands: Commands, assists: Res<Assists>) {
+    commands
+        .spawn((
+            DespawnOnExit(Screen::Assist),
+            Node {
+                display: Display::Flex,
+                flex_direction: FlexDirection::Column,
+                width: percent(100),
+                height: percent(100),
+                row_gap: px(10),
+                ..Default::default()
+            },
+            ThemeBackgroundColor(tokens::WINDOW_BG),
+        ))
+        .with_children(|parent| {
+            parent.spawn(Text::new(
+                "Assists make the crypt more forgiving, runs using them are marked.\n\nGame speed:",
+            ));
+            parent
+                .spawn(slider(
+                    SliderProps {
+                        value: assists.game_speed,
+                        min: MIN_GAME_SPEED,
+                        max: 1.0,
+                    },
+                    (SliderStep(0.05), SliderPrecision(2)),
+                ))
+                .observe(set_game_speed);
+            let mut invincible = parent.spawn(checkbox((), Spawn(Text::new("Invincible"))));
+            if assists.invincible {
+                invincible.insert(Checked);
+            }
+            invincible.observe(set_invincible);
+            let mut infinite_timer = parent.spawn(checkbox((), Spawn(Text::new("Infinite timer"))));
+            if assists.infinite_timer {
+                infinite_timer.insert(Checked);
+            }
+            infinite_timer.observe(set_infinite_timer);
+            parent.spawn((
+                button(ButtonProps::default(), (), Spawn(Text::new("Back"))),
+                observe(go_to_main),
+            ));
+        });
+}
+
+fn set_game_speed(
+    change: On<ValueChange<f32>>,
+    mut commands: Commands,
+    mut assists: ResMut<Assists>,
+) {
+    assists.game_speed = change.value;
+    commands
+        .entity(change.source)
+        .insert(SliderValue(change.value));
+}
+
+fn set_invincible(
+    change: On<ValueChange<bool>>,
+    mut commands: Commands,
+    mut assists: ResMut<Assists>,
+) {
+    assists.invincible = change.value;
+    set_checked(&mut commands, change.source, change.value);
+}
+
+fn set_infinite_timer(
+    change: On<ValueChange<bool>>,
+    mut commands: Commands,
+    mut assists: ResMut<Assists>,
+) {
+    assists.infinite_timer = change.value;
+    set_checked(&mut commands, change.source, change.value);
+}
+
+/// Shows the new value of a checkbox, feathers leaves that to the observer.
+pub(crate) fn set_checked(commands: &mut Commands, checkbox: Entity, checked: bool) {
+    if checked {
+        commands.entity(checkbox).insert(Checked);
+    } else {
+        commands.entity(checkbox).remove::<Checked>();
+    }
+}
@@ -0,0 +1,283 @@
+use std::{collections::BTreeMap, f32::consts::TAU};
+
+use bevy::{math::Affine2, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Opts, RequiredAssets,
+    assist::Assists,
+    levels::{CurrentLevel, LevelScreens},
+    player::PlayerMarker,
+    screens::Screen,
+    telemetry::Telemetry,
+    terrain::{VoxelizedView, update_terrain, world_to_voxel},
+    transition::{Transition, TransitionStyle},
+};
+
+/// Seconds the death overlay stays, it fades out over the last of them.
+const OVERLAY_SECONDS: f32 = 2.0;
+const OVERLAY_FADE: f32 = 0.5;
+/// The player bursts into this many pieces per side.
+const PIECES: u32 = 4;
+/// Seconds until the pieces have faded.
+const BURST_SECONDS: f32 = 0.6;
+/// How fast the pieces fly apart, in pixels per second.
+const BURST_SPEED: f32 = 120.0;
+
+/// Turns [`PlayerDeath`] into a restart of the level and tells the player what killed them.
+pub struct DeathPlugin;
+
+impl Plugin for DeathPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<PlayerDeath>();
+        app.init_resource::<DeathCounter>();
+        app.add_systems(OnEnter(Screen::Gameplay), reset_death_counter);
+        app.add_systems(
+            Update,
+            (
+                crush_player
+                    .after(update_terrain)
+                    .run_if(in_state(LevelScreens::Level)),
+                fade_death_overlay,
+                scatter_pieces,
+            ),
+        );
+        // after everything that kills the player in Update
+        app.add_systems(
+            PostUpdate,
+            handle_death.run_if(in_state(LevelScreens::Level)),
+        );
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DeathCause {
+    Lava,
+    OutOfBounds,
+    TimeUp,
+    /// The terrain grew into the player.
+    Crushed,
+}
+
+impl DeathCause {
+    pub fn describe(self) -> &'static str {
+        match self {
+            DeathCause::Lava => "Burned in the lava",
+            DeathCause::OutOfBounds => "Lost outside the crypt",
+            DeathCause::TimeUp => "The poem ran out",
+            DeathCause::Crushed => "Crushed by the crypt",
+        }
+    }
+}
+
+/// The player died, [`handle_death`] restarts the level.
+#[derive(Message, Clone, Copy, Debug)]
+pub struct PlayerDeath {
+    pub cause: DeathCause,
+}
+
+/// Deaths per level during this run, by level index.
+#[derive(Resource, Default)]
+pub struct DeathCounter(pub BTreeMap<u32, u32>);
+
+#[derive(Component)]
+struct DeathOverlay {
+    timer: Timer,
+}
+
+/// A piece of the burst player, moved on real time since the game is frozen meanwhile.
+#[derive(Component)]
+struct Piece {
+    velocity: Vec2,
+    spin: f32,
+    elapsed: f32,
+}
+
+fn reset_death_counter(mut counter: ResMut<DeathCounter>) {
+    counter.0.clear();
+}
+
+/// The middle of the player ended up in terrain, after a generation the shrinking bubble no
+/// longer protected from or a rewind.
+fn crush_player(
+    player: Single<&Transform, With<PlayerMarker>>,
+    terrain: Single<&VoxelizedView>,
+    opts: Res<Opts>,
+    assists: Res<Assists>,
+    mut deaths: MessageWriter<PlayerDeath>,
+) {
+    if opts.noclip || opts.god_mode || assists.invincible {
+        return;
+    }
+    if let Some(cell) = world_to_voxel(player.translation.xy())
+        && terrain.get(cell.x, cell.y)
+    {
+        deaths.write(PlayerDeath {
+            cause: DeathCause::Crushed,
+        });
+    }
+}
+
+/// Only the first death of a frame counts, the level restarts behind a [`Transition`].
+fn handle_death(
+    mut commands: Commands,
+    mut deaths: MessageReader<PlayerDeath>,
+    mut transition: ResMut<Transition>,
+    mut counter: ResMut<DeathCounter>,
+    mut telemetry: ResMut<Telemetry>,
+    current_level: Res<CurrentLevel>,
+    mut player: Query<
+        (&Transform, &MeshMaterial2d<ColorMaterial>, &mut Visibility),
+        With<PlayerMarker>,
+    >,
+    overlays: Query<Entity, With<DeathOverlay>>,
+    assets: Res<RequiredAssets>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let Some(&PlayerDeath { cause }) = deaths.read().next() else {
+        return;
+    };
+    deaths.clear();
+    // still dying, or already on the way out of the level
+    if transition.is_running() {
+        return;
+    }
+    transition.start(LevelScreens::Restart, TransitionStyle::Vignette);
+
+    let count = counter.0.entry(current_level.0).or_default();
+    *count += 1;
+    if let Ok((transform, material, mut visibility)) = player.single_mut() {
+        telemetry.died(cause, transform.translation.xy());
+        *visibility = Visibility::Hidden;
+        let texture = materials.get(&material.0).and_then(|m| m.texture.clone());
+        burst(
+            &mut commands,
+            transform,
+            texture,
+            &mut meshes,
+            &mut materials,
+        );
+    }
+
+    for overlay in &overlays {
+        commands.entity(overlay).despawn();
+    }
+    let counted = if *count == 1 {
+        "1 death".to_string()
+    } else {
+        format!("{count} deaths")
+    };
+    // outlives the level, so it stays while the level is rebuilt
+    commands.spawn((
+        DespawnOnExit(Screen::Gameplay),
+        // above the transition
+        GlobalZIndex(101),
+        DeathOverlay {
+            timer: Timer::from_seconds(OVERLAY_SECONDS, TimerMode::Once),
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            width: percent(100.0),
+            top: percent(40.0),
+            justify_content: JustifyContent::Center,
+            ..Default::default()
+        },
+        children![(
+            Text::new(format!("{}\n{counted} on this level", cause.describe())),
+            TextFont {
+                font: assets.font.clone().unwrap(),
+                font_size: 30.0,
+                ..Default::default()
+            },
+            TextLayout::new_with_justify(Justify::Center),
+            TextColor(Color::WHITE),
+        )],
+    ));
+}
+
+/// Splits the 20x20 player into [`PIECES`]² pieces of its texture flying apart.
+fn burst(
+    commands: &mut Commands,
+    player: &Transform,
+    texture: Option<Handle<Image>>,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+) {
+    let size = 20.0 / PIECES as f32;
+    let mesh = meshes.add(Rectangle::new(size, size));
+    for i in 0..PIECES {
+        for j in 0..PIECES {
+            // the center of the piece relative to the player, y up
+            let offset = Vec2::new(
+                (i as f32 + 0.5) * size - 10.0,
+                10.0 - (j as f32 + 0.5) * size,
+            );
+            let offset = (player.rotation * offset.extend(0.0)).xy();
+            let uv = Vec2::new(i as f32, j as f32) / PIECES as f32;
+            let material = materials.add(ColorMaterial {
+                texture: texture.clone(),
+                uv_transform: Affine2::from_scale_angle_translation(
+                    Vec2::splat(1.0 / PIECES as f32),
+                    0.0,
+                    uv,
+                ),
+                ..Default::default()
+            });
+            let spread = (i * PIECES + j) as f32 / (PIECES * PIECES) as f32;
+            commands.spawn((
+                DespawnOnExit(LevelScreens::Level),
+                Transform {
+                    translation: player.translation + offset.extend(0.0),
+                    ..*player
+                },
+                Mesh2d(mesh.clone()),
+                MeshMaterial2d(material),
+                Piece {
+                    velocity: offset.normalize_or_zero() * BURST_SPEED * (0.6 + 0.4 * spread),
+                    spin: (spread - 0.5) * TAU,
+                    elapsed: 0.0,
+                },
+            ));
+        }
+    }
+}
+
+fn scatter_pieces(
+    mut pieces: Query<(&mut Piece, &mut Transform, &MeshMaterial2d<ColorMaterial>)>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    time: Res<Time<Real>>,
+) {
+    let dt = time.delta_secs();
+    for (mut piece, mut transform, material) in &mut pieces {
+        piece.elapsed += dt;
+        transform.translation += (piece.velocity * dt).extend(0.0);
+        transform.rotate_z(piece.spin * dt);
+        if let Some(material) = materials.get_mut(&material.0) {
+            let alpha = 1.0 - (piece.elapsed / BURST_SECONDS).min(1.0);
+            material.color.set_alpha(alpha);
+        }
+    }
+}
+
+/// Runs on real time, the game is frozen for the first part of it.
+fn fade_death_overlay(
+    mut commands: Commands,
+    mut overlays: Query<(Entity, &mut DeathOverlay, &Children)>,
+    mut colors: Query<&mut TextColor>,
+    time: Res<Time<Real>>,
+) {
+    for (entity, mut overlay, children) in &mut overlays {
+        overlay.timer.tick(time.delta());
+        if overlay.timer.is_finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        let alpha = (overlay.timer.remaining_secs() / OVERLAY_FADE).min(1.0);
+        for &child in children {
+            if let Ok(mut color) = colors.get_mut(child) {
+                color.0.set_alpha(alpha);
+            }
+        }
+    }
+}
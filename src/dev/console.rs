@@ -0,0 +1,281 @@
+use std::path::PathBuf;
+
+use avian2d::prelude::LinearVelocity;
+use bevy::{
+    input::{
+        ButtonState,
+        keyboard::{Key, KeyboardInput},
+    },
+    prelude::*,
+};
+
+use crate::{
+    levels::{CurrentLevel, LevelScreens},
+    player::PlayerMarker,
+    snapshot::LevelSnapshot,
+    speedrun::RunTimer,
+    terrain::{
+        FinishMarker, Killzones, RequiredFinishes, TerrainFrozen, TerrainSeed, TimeDiluationMap,
+        UpdateTimer, VoxelizedView, cell_center,
+    },
+    transition::{Transition, TransitionStyle},
+};
+
+/// Lines of history kept above the prompt.
+const HISTORY: usize = 8;
+
+const HELP: &str = "commands: level <1-4>, restart, tp <x> <y>, give bones, freeze terrain, timescale <f>, snapshot <file>";
+
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Console>();
+        app.add_systems(Startup, spawn_console);
+        app.add_systems(Update, (read_console, update_console).chain());
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Console {
+    open: bool,
+    input: String,
+    history: Vec<String>,
+}
+
+impl Console {
+    fn print(&mut self, line: impl Into<String>) {
+        self.history.push(line.into());
+        let overflow = self.history.len().saturating_sub(HISTORY);
+        self.history.drain(..overflow);
+    }
+}
+
+/// Run condition keeping keystrokes meant for the console away from the player, the console
+/// only exists with `--dev`.
+pub fn console_closed(console: Option<Res<Console>>) -> bool {
+    console.is_none_or(|console| !console.open)
+}
+
+#[derive(Component)]
+struct ConsoleText;
+
+fn spawn_console(mut commands: Commands) {
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            top: px(0),
+            left: px(0),
+            width: percent(100),
+            padding: UiRect::all(px(6)),
+            ..Default::default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.8)),
+        GlobalZIndex(20),
+        Visibility::Hidden,
+        children![(
+            Text::new(""),
+            TextFont {
+                font_size: 14.0,
+                ..Default::default()
+            },
+            ConsoleText,
+        )],
+    ));
+}
+
+fn update_console(
+    console: Res<Console>,
+    text: Single<(&mut Text, &ChildOf), With<ConsoleText>>,
+    mut visibility: Query<&mut Visibility>,
+) {
+    if !console.is_changed() {
+        return;
+    }
+    let (mut text, parent) = text.into_inner();
+    if let Ok(mut visibility) = visibility.get_mut(parent.parent()) {
+        *visibility = if console.open {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+    }
+    let mut lines = console.history.clone();
+    lines.push(format!("> {}_", console.input));
+    text.0 = lines.join("\n");
+}
+
+enum ConsoleCommand {
+    Level(u32),
+    Restart,
+    Teleport(UVec2),
+    GiveBones,
+    FreezeTerrain,
+    Timescale(f32),
+    /// Writes the level as RON (`.ron`) or binary snapshot, to attach to bug reports.
+    Snapshot(PathBuf),
+}
+
+impl ConsoleCommand {
+    fn parse(line: &str) -> Result<ConsoleCommand, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["level", n] => match n.parse() {
+                Ok(n @ 1..=4) => Ok(ConsoleCommand::Level(n)),
+                _ => Err(format!("{n} is not a level (1-4)")),
+            },
+            ["restart"] => Ok(ConsoleCommand::Restart),
+            ["tp", x, y] => match (x.parse(), y.parse()) {
+                (Ok(x @ 0..128), Ok(y @ 0..128)) => Ok(ConsoleCommand::Teleport(UVec2::new(x, y))),
+                _ => Err(format!("{x} {y} is not a cell (0-127)")),
+            },
+            ["give", "bones"] => Ok(ConsoleCommand::GiveBones),
+            ["freeze", "terrain"] => Ok(ConsoleCommand::FreezeTerrain),
+            ["timescale", f] => match f.parse::<f32>() {
+                Ok(f) if f >= 0.0 => Ok(ConsoleCommand::Timescale(f)),
+                _ => Err(format!("{f} is not a time scale")),
+            },
+            ["snapshot", file] => Ok(ConsoleCommand::Snapshot(PathBuf::from(file))),
+            _ => Err(HELP.to_string()),
+        }
+    }
+}
+
+fn read_console(
+    mut console: ResMut<Console>,
+    mut input: MessageReader<KeyboardInput>,
+    mut commands: Commands,
+    state: Option<Res<State<LevelScreens>>>,
+    mut transition: ResMut<Transition>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut required_finishes: ResMut<RequiredFinishes>,
+    mut frozen: ResMut<TerrainFrozen>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    mut player: Query<(&mut Transform, &mut LinearVelocity), With<PlayerMarker>>,
+    finishes: Query<(Entity, &Transform), (With<FinishMarker>, Without<PlayerMarker>)>,
+    terrain: Query<(&VoxelizedView, &TimeDiluationMap, &UpdateTimer)>,
+    killzones: Query<&Killzones>,
+    seed: Res<TerrainSeed>,
+    mut run: ResMut<RunTimer>,
+) {
+    let mut submitted = Vec::new();
+    for key in input.read() {
+        if key.state != ButtonState::Pressed {
+            continue;
+        }
+        if key.key_code == KeyCode::Backquote {
+            console.open = !console.open;
+            continue;
+        }
+        if !console.open {
+            continue;
+        }
+        match &key.logical_key {
+            Key::Enter => submitted.push(std::mem::take(&mut console.input)),
+            Key::Backspace => {
+                console.input.pop();
+            }
+            Key::Escape => console.open = false,
+            _ => {
+                if let Some(text) = &key.text {
+                    console
+                        .input
+                        .extend(text.chars().filter(|c| !c.is_control()));
+                }
+            }
+        }
+    }
+
+    let in_level = state.is_some_and(|s| *s.get() == LevelScreens::Level);
+    for line in submitted {
+        if line.trim().is_empty() {
+            continue;
+        }
+        console.print(format!("> {line}"));
+        let command = match ConsoleCommand::parse(&line) {
+            Ok(command) => command,
+            Err(e) => {
+                console.print(e);
+                continue;
+            }
+        };
+        let needs_level = matches!(
+            command,
+            ConsoleCommand::Level(_)
+                | ConsoleCommand::Restart
+                | ConsoleCommand::Teleport(_)
+                | ConsoleCommand::GiveBones
+                | ConsoleCommand::Snapshot(_)
+        );
+        if needs_level && !in_level {
+            console.print("only available while playing a level");
+            continue;
+        }
+        let leaves_level = matches!(
+            command,
+            ConsoleCommand::Level(_) | ConsoleCommand::Restart | ConsoleCommand::GiveBones
+        );
+        // the running transition would switch the state right after ours
+        if leaves_level && transition.is_running() {
+            console.print("the level is already ending");
+            continue;
+        }
+        // restarting and looking at the level give the run no advantage
+        if !matches!(
+            command,
+            ConsoleCommand::Restart | ConsoleCommand::Snapshot(_)
+        ) {
+            run.console_used = true;
+        }
+        match command {
+            ConsoleCommand::Level(n) => {
+                current_level.0 = n - 1;
+                transition.start(LevelScreens::Restart, TransitionStyle::Fade);
+            }
+            ConsoleCommand::Restart => {
+                transition.start(LevelScreens::Restart, TransitionStyle::Fade);
+            }
+            ConsoleCommand::Teleport(cell) => {
+                for (mut transform, mut velocity) in &mut player {
+                    let p = cell_center(cell);
+                    transform.translation.x = p.x;
+                    transform.translation.y = p.y;
+                    velocity.0 = Vec2::ZERO;
+                }
+            }
+            ConsoleCommand::GiveBones => {
+                for (finish, _) in &finishes {
+                    commands.entity(finish).despawn();
+                }
+                required_finishes.0 = 0;
+                transition.start(LevelScreens::Intermission, TransitionStyle::Iris);
+            }
+            ConsoleCommand::FreezeTerrain => {
+                frozen.0 = !frozen.0;
+                let state = if frozen.0 { "frozen" } else { "thawed" };
+                console.print(format!("terrain {state}"));
+            }
+            ConsoleCommand::Timescale(f) => {
+                virtual_time.set_relative_speed(f);
+                console.print(format!("time scale {f}"));
+            }
+            ConsoleCommand::Snapshot(path) => {
+                let (Ok(terrain), Ok(killzones)) = (terrain.single(), killzones.single()) else {
+                    console.print("the level is not built yet");
+                    continue;
+                };
+                let snapshot = LevelSnapshot::capture(
+                    current_level.0,
+                    seed.0,
+                    terrain,
+                    killzones,
+                    finishes.iter().map(|(_, bone)| bone.translation.xy()),
+                );
+                match snapshot.save(&path) {
+                    Ok(()) => console.print(format!("level written to {}", path.display())),
+                    Err(e) => console.print(e),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use bevy::prelude::*;
+
+use crate::{
+    Opts,
+    player::PlayerMarker,
+    screens::Screen,
+    terrain::{
+        LastGeneration, PlayerBubble, TerrainMaterial, TimeDiluationMap, cell_center,
+        update_terrain_material,
+    },
+};
+
+pub struct HeatmapPlugin;
+
+impl Plugin for HeatmapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Heatmap>();
+        app.add_systems(Startup, |mut heatmap: ResMut<Heatmap>, opts: Res<Opts>| {
+            heatmap.0 = opts.heatmap;
+        });
+        app.add_systems(Update, toggle_heatmap);
+        app.add_systems(
+            Update,
+            (apply_heatmap.after(update_terrain_material), draw_activity)
+                .run_if(in_state(Screen::Gameplay).and(|heatmap: Res<Heatmap>| heatmap.0)),
+        );
+    }
+}
+
+/// Draws the local time of every voxel as a color ramp instead of the terrain.
+#[derive(Resource, Default)]
+pub struct Heatmap(pub bool);
+
+fn toggle_heatmap(keys: Res<ButtonInput<KeyCode>>, mut heatmap: ResMut<Heatmap>) {
+    if keys.just_pressed(KeyCode::F4) {
+        heatmap.0 = !heatmap.0;
+    }
+}
+
+/// Switches the terrain shader into heatmap mode, the ramp spans the current range of the field.
+fn apply_heatmap(
+    terrain: Query<(&MeshMaterial2d<TerrainMaterial>, &TimeDiluationMap)>,
+    mut materials: ResMut<Assets<TerrainMaterial>>,
+) {
+    for (material, time) in &terrain {
+        if let Some(material) = materials.get_mut(&material.0) {
+            let (min, max) = time.range();
+            material.level.y = 1;
+            material.global_time.y = min;
+            material.global_time.z = max;
+        }
+    }
+}
+
+fn draw_activity(
+    mut gizmos: Gizmos,
+    last_generation: Res<LastGeneration>,
+    player: Query<&Transform, With<PlayerMarker>>,
+    times: Query<&TimeDiluationMap>,
+) {
+    let cell = Vec2::splat(16.0);
+    for &born in &last_generation.born {
+        gizmos.rect_2d(cell_center(born), cell, Color::srgb(0.22, 0.72, 0.39));
+    }
+    for &killed in &last_generation.killed {
+        gizmos.rect_2d(cell_center(killed), cell, Color::srgb(0.69, 0.24, 0.33));
+    }
+    if let Ok(player) = player.single() {
+        let p = player.translation.xy();
+        let bubble = times
+            .iter()
+            .next()
+            .map_or_else(PlayerBubble::default, |t| t.bubble);
+        gizmos.circle_2d(p, bubble.time_radius, Color::srgb(0.45, 0.94, 0.97));
+        gizmos.circle_2d(p, bubble.protection_radius, Color::srgb(1.0, 0.8, 0.46));
+    }
+}
@@ -0,0 +1,21 @@
+use bevy::{diagnostic::FrameTimeDiagnosticsPlugin, prelude::*};
+
+mod console;
+mod heatmap;
+mod overlay;
+
+pub use console::console_closed;
+
+/// Debug overlay (F3), time field heatmap (F4) and developer console (`).
+pub struct DevPlugin;
+
+impl Plugin for DevPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(FrameTimeDiagnosticsPlugin::default());
+        app.add_plugins((
+            overlay::OverlayPlugin,
+            console::ConsolePlugin,
+            heatmap::HeatmapPlugin,
+        ));
+    }
+}
@@ -0,0 +1,93 @@
+use bevy::{
+    diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin},
+    prelude::*,
+};
+
+use crate::{
+    player::PlayerMarker,
+    terrain::{RequiredFinishes, TerrainTimings, TimeDiluationMap, VoxelizedView, world_to_voxel},
+};
+
+pub struct OverlayPlugin;
+
+impl Plugin for OverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_overlay);
+        app.add_systems(Update, (toggle_overlay, update_overlay).chain());
+    }
+}
+
+#[derive(Component)]
+struct OverlayText;
+
+fn spawn_overlay(mut commands: Commands) {
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: px(10),
+            left: px(10),
+            padding: UiRect::all(px(6)),
+            ..Default::default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+        GlobalZIndex(10),
+        Visibility::Hidden,
+        children![(
+            Text::new(""),
+            TextFont {
+                font_size: 14.0,
+                ..Default::default()
+            },
+            OverlayText,
+        )],
+    ));
+}
+
+fn toggle_overlay(
+    keys: Res<ButtonInput<KeyCode>>,
+    text: Single<&ChildOf, With<OverlayText>>,
+    mut visibility: Query<&mut Visibility>,
+) {
+    if !keys.just_pressed(KeyCode::F3) {
+        return;
+    }
+    if let Ok(mut visibility) = visibility.get_mut(text.parent()) {
+        visibility.toggle_visible_hidden();
+    }
+}
+
+fn update_overlay(
+    mut text: Single<(&mut Text, &InheritedVisibility), With<OverlayText>>,
+    diagnostics: Res<DiagnosticsStore>,
+    timings: Res<TerrainTimings>,
+    finishes: Res<RequiredFinishes>,
+    terrain: Query<(&VoxelizedView, &TimeDiluationMap)>,
+    player: Query<&Transform, With<PlayerMarker>>,
+) {
+    if !text.1.get() {
+        return;
+    }
+    let fps = diagnostics
+        .get(&FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|fps| fps.smoothed())
+        .unwrap_or_default();
+    let mut lines = vec![
+        format!("fps: {fps:.0}"),
+        format!("update_time: {:.2?}", timings.update_time),
+        format!("update_terrain: {:.2?}", timings.update_terrain),
+        format!("bones left: {}", finishes.0),
+    ];
+    if let Ok((voxels, time)) = terrain.single() {
+        lines.push(format!("voxels: {}", voxels.total()));
+        if let Ok(player) = player.single() {
+            match world_to_voxel(player.translation.xy()) {
+                Some(cell) => {
+                    lines.push(format!("cell: {}, {}", cell.x, cell.y));
+                    lines.push(format!("local time: {:.2}", time.get(cell.x, cell.y)));
+                }
+                None => lines.push("cell: outside".to_string()),
+            }
+        }
+    }
+    text.0.0 = lines.join("\n");
+}
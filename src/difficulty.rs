@@ -0,0 +1,207 @@
+use bevy::prelude::*;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    adaptive::ease_level,
+    levels::LevelScreens,
+    player::PlayerMarker,
+    player_controller::JUMP_VELOCITY,
+    terrain::{
+        FinishMarker, Killzones, OUT_OF_BOUNDS, PlayerBubble, TerrainSeed, TimeDiluationMap,
+        UPDATE_INTERVAL, UpdateTimer, VoxelizedView, cell_center, replace_collider, update_terrain,
+        update_terrain_material, update_time,
+    },
+};
+
+/// Chance per generation that lava flows into a free voxel next to it on Nightmare.
+const LAVA_SPREAD: f32 = 0.1;
+/// Lava does not flow closer than this to a bone.
+const BONE_CLEARANCE: f32 = 60.0;
+/// Seconds until the bubble shrank to [`SHRUNK_BUBBLE`] on Nightmare.
+const SHRINK_DURATION: f32 = 60.0;
+/// The bubble at the end of the shrinking, relative to the one the level started with.
+const SHRUNK_BUBBLE: f32 = 0.6;
+
+pub struct DifficultyPlugin;
+
+impl Plugin for DifficultyPlugin {
+    fn build(&self, app: &mut App) {
+        let nightmare = |difficulty: Res<Difficulty>| *difficulty == Difficulty::Nightmare;
+        app.add_systems(
+            OnEnter(LevelScreens::Level),
+            start_nightmare.after(ease_level).run_if(nightmare),
+        );
+        app.add_systems(
+            Update,
+            (
+                shrink_bubble.before(update_time),
+                spread_lava
+                    .after(update_terrain)
+                    .before(update_terrain_material),
+            )
+                .run_if(in_state(LevelScreens::Level).and(nightmare)),
+        );
+    }
+}
+
+/// How forgiving a run is, picked in the main menu or with `--difficulty`.
+#[derive(
+    Resource,
+    ValueEnum,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    Default,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+pub enum Difficulty {
+    /// More time, slower terrain and a wider bubble.
+    Dreamer,
+    /// The game as the levels were designed.
+    #[default]
+    Wanderer,
+    /// Less time, faster terrain, spreading lava and a shrinking bubble.
+    Nightmare,
+}
+
+impl Difficulty {
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Dreamer => "Dreamer",
+            Difficulty::Wanderer => "Wanderer",
+            Difficulty::Nightmare => "Nightmare",
+        }
+    }
+
+    /// The difficulty after this one in the menu.
+    pub fn next(self) -> Difficulty {
+        match self {
+            Difficulty::Dreamer => Difficulty::Wanderer,
+            Difficulty::Wanderer => Difficulty::Nightmare,
+            Difficulty::Nightmare => Difficulty::Dreamer,
+        }
+    }
+
+    /// Seconds until the poem runs out and the level restarts.
+    pub fn poem_duration(self) -> f32 {
+        match self {
+            Difficulty::Dreamer => 120.0,
+            Difficulty::Wanderer => 90.0,
+            Difficulty::Nightmare => 70.0,
+        }
+    }
+
+    /// Seconds between two generations of the terrain.
+    pub fn update_interval(self) -> f32 {
+        match self {
+            Difficulty::Dreamer => UPDATE_INTERVAL * 1.3,
+            Difficulty::Wanderer => UPDATE_INTERVAL,
+            Difficulty::Nightmare => UPDATE_INTERVAL * 0.8,
+        }
+    }
+
+    pub fn bubble(self) -> PlayerBubble {
+        let bubble = PlayerBubble::default();
+        match self {
+            Difficulty::Dreamer => bubble.scaled(1.2),
+            Difficulty::Wanderer => bubble,
+            Difficulty::Nightmare => bubble.scaled(0.85),
+        }
+    }
+
+    /// How far the player can leave the level before it restarts, jumps can be repeated in the air
+    /// so a wider box leaves more room to get back.
+    pub fn bounds(self) -> f32 {
+        match self {
+            Difficulty::Dreamer => OUT_OF_BOUNDS * 1.5,
+            Difficulty::Wanderer | Difficulty::Nightmare => OUT_OF_BOUNDS,
+        }
+    }
+
+    pub fn jump_velocity(self) -> f32 {
+        match self {
+            Difficulty::Dreamer => JUMP_VELOCITY * 1.1,
+            Difficulty::Wanderer => JUMP_VELOCITY,
+            Difficulty::Nightmare => JUMP_VELOCITY * 0.9,
+        }
+    }
+}
+
+/// The state of Nightmare in the current attempt at a level, on the terrain.
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct NightmareTerrain {
+    /// The bubble the level started with, it shrinks over [`SHRINK_DURATION`].
+    full_bubble: PlayerBubble,
+    shrink: Timer,
+    /// Generations the lava spread in, so every attempt spreads the same way.
+    generation: u64,
+}
+
+fn start_nightmare(mut commands: Commands, terrain: Query<(Entity, &TimeDiluationMap)>) {
+    for (entity, time) in &terrain {
+        commands.entity(entity).insert(NightmareTerrain {
+            full_bubble: time.bubble,
+            shrink: Timer::from_seconds(SHRINK_DURATION, TimerMode::Once),
+            generation: 0,
+        });
+    }
+}
+
+fn shrink_bubble(
+    mut terrain: Query<(&mut TimeDiluationMap, &mut NightmareTerrain)>,
+    clock: Res<Time>,
+) {
+    for (mut time, mut nightmare) in &mut terrain {
+        nightmare.shrink.tick(clock.delta());
+        let scale = 1.0 + (SHRUNK_BUBBLE - 1.0) * nightmare.shrink.fraction();
+        time.bubble = nightmare.full_bubble.scaled(scale);
+    }
+}
+
+/// Lets the lava flow every generation, it keeps away from the player and the bones.
+fn spread_lava(
+    mut commands: Commands,
+    mut terrain: Query<(
+        &VoxelizedView,
+        &UpdateTimer,
+        &TimeDiluationMap,
+        &Transform,
+        &mut NightmareTerrain,
+    )>,
+    mut killzones: Single<(Entity, &mut Killzones)>,
+    player: Single<&Transform, With<PlayerMarker>>,
+    finishes: Query<&Transform, With<FinishMarker>>,
+    seed: Res<TerrainSeed>,
+) {
+    let finishes: Vec<Vec2> = finishes.iter().map(|f| f.translation.xy()).collect();
+    for (voxels, timer, time, transform, mut nightmare) in &mut terrain {
+        if !timer.0.just_finished() {
+            continue;
+        }
+        nightmare.generation += 1;
+        let player = player.translation.xy();
+        let offset = transform.translation.xy();
+        let protection = time.bubble.protection_radius;
+        let (entity, ref mut lava) = *killzones;
+        lava.spread(
+            voxels,
+            LAVA_SPREAD,
+            seed.0 ^ nightmare.generation.wrapping_mul(0x9E37_79B9),
+            |x, y| {
+                let p = cell_center(UVec2::new(x, y)) + offset;
+                p.distance_squared(player) < protection * protection
+                    || finishes
+                        .iter()
+                        .any(|f| f.distance_squared(p) < BONE_CLEARANCE * BONE_CLEARANCE)
+            },
+        );
+        replace_collider(&mut commands, entity, lava.collider());
+    }
+}
@@ -2,8 +2,20 @@ use avian2d::prelude::Gravity;
 use avian2d::{PhysicsPlugins, prelude::PhysicsDebugPlugin};
 use bevy::{prelude::*, sprite_render::Material2dPlugin};
 
+use crate::adaptive::AdaptivePlugin;
+use crate::anchor::AnchorPlugin;
+use crate::death::DeathPlugin;
+use crate::dev::console_closed;
+use crate::difficulty::DifficultyPlugin;
+use crate::ghost::GhostPlugin;
+use crate::livesplit::LiveSplitPlugin;
 use crate::main_screen::camera_intro_zoom;
 use crate::player::sync_camera_to_player;
+use crate::practice::PracticePlugin;
+use crate::replay::ReplayPlugin;
+use crate::rewind::RewindPlugin;
+use crate::speedrun::SpeedrunPlugin;
+use crate::telemetry::TelemetryPlugin;
 use crate::terrain::out_of_bounds;
 use crate::{
     Opts,
@@ -11,9 +23,15 @@ use crate::{
     player::spawn_player,
     player_controller::update_player_position,
     screens::Screen,
-    terrain::{RequiredFinishes, TerrainMaterial, spawn_level, update_terrain, update_time},
+    terrain::{
+        LastGeneration, RequiredFinishes, TerrainFrozen, TerrainMaterial, TerrainSeed,
+        TerrainTimings, spawn_level, update_terrain, update_terrain_material, update_time,
+    },
 };
 
+/// Acceleration towards the ground, in pixels per second squared.
+pub const GRAVITY: f32 = 9.81 * 50.0;
+
 pub struct GameplayPlugin {
     pub opts: Opts,
 }
@@ -21,40 +39,49 @@ pub struct GameplayPlugin {
 impl Plugin for GameplayPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(PhysicsPlugins::default().with_length_unit(20.0))
-            .insert_resource(Gravity(Vec2::NEG_Y * (9.81 * 50.0)));
+            .insert_resource(Gravity(Vec2::NEG_Y * GRAVITY));
 
         app.add_plugins(Material2dPlugin::<TerrainMaterial>::default());
         app.add_plugins(LevelPlugin);
+        app.add_plugins(AnchorPlugin);
+        app.add_plugins(DeathPlugin);
+        app.add_plugins(RewindPlugin);
+        app.add_plugins(AdaptivePlugin);
+        app.add_plugins(DifficultyPlugin);
+        app.add_plugins(SpeedrunPlugin);
+        app.add_plugins(LiveSplitPlugin);
+        app.add_plugins(ReplayPlugin);
+        app.add_plugins(GhostPlugin);
+        app.add_plugins(PracticePlugin);
+        app.add_plugins(TelemetryPlugin);
         if self.opts.debug_colliders {
             app.add_plugins(PhysicsDebugPlugin);
         }
         app.insert_resource(RequiredFinishes(0));
+        app.insert_resource(TerrainSeed(self.opts.seed));
+        app.init_resource::<TerrainTimings>();
+        app.init_resource::<TerrainFrozen>();
+        app.init_resource::<LastGeneration>();
         app.add_systems(
             OnEnter(LevelScreens::Level),
             (spawn_level, spawn_player).chain(),
         );
         app.add_systems(
             Update,
-            (update_time, update_terrain)
+            (update_time, update_terrain, update_terrain_material)
                 .chain()
                 .run_if(in_state(Screen::Gameplay)),
         );
         app.add_systems(
             Update,
-            (update_player_position, sync_camera_to_player)
+            (
+                update_player_position.run_if(console_closed),
+                sync_camera_to_player,
+            )
                 .chain()
                 .run_if(in_state(Screen::Gameplay)),
         );
         app.add_systems(Update, camera_intro_zoom.run_if(in_state(Screen::Gameplay)));
         app.add_systems(Update, out_of_bounds.run_if(in_state(Screen::Gameplay)));
-        app.insert_resource(RunStartTime(0.0));
-        app.add_systems(OnEnter(Screen::Gameplay), set_start);
     }
 }
-
-#[derive(Resource)]
-pub struct RunStartTime(pub f32);
-
-fn set_start(mut start: ResMut<RunStartTime>, time: Res<Time>) {
-    start.0 = time.elapsed_secs();
-}
@@ -0,0 +1,195 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Opts,
+    assist::Assists,
+    difficulty::Difficulty,
+    levels::{CurrentLevel, LevelScreens},
+    player::{PlayerMarker, spawn_player},
+    save::SaveFile,
+    speedrun::{RunTimer, Standing},
+};
+
+/// Opacity of the ghost.
+const GHOST_ALPHA: f32 = 0.35;
+
+/// Replays the fastest attempt at a level as a translucent head next to the player.
+///
+/// The ghost has no collider and no [`PlayerMarker`], so it neither collects bones nor holds
+/// the crypt still.
+pub struct GhostPlugin;
+
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GhostRecorder>();
+        app.add_systems(
+            OnEnter(LevelScreens::Level),
+            (reset_ghost_recorder, spawn_ghost.after(spawn_player)),
+        );
+        app.add_systems(
+            FixedUpdate,
+            sample_player.run_if(in_state(LevelScreens::Level)),
+        );
+        app.add_systems(Update, move_ghost.run_if(in_state(LevelScreens::Level)));
+        app.add_systems(OnEnter(LevelScreens::Intermission), keep_ghost);
+    }
+}
+
+/// The path of the player through an attempt at a level.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Ghost {
+    /// Seconds between two samples, the fixed timestep of the attempt.
+    pub interval: f32,
+    /// Positions of the player, from the start of the attempt to the last bone.
+    pub path: Vec<[f32; 2]>,
+}
+
+impl Ghost {
+    pub fn duration(&self) -> f32 {
+        self.interval * self.path.len().saturating_sub(1) as f32
+    }
+
+    /// Where the player was `seconds` into the attempt, `None` once it was over.
+    pub fn position(&self, seconds: f32) -> Option<Vec2> {
+        let t = seconds / self.interval;
+        let i = t.floor() as usize;
+        let from = Vec2::from(*self.path.get(i)?);
+        let to = self.path.get(i + 1).map_or(from, |&p| Vec2::from(p));
+        Some(from.lerp(to, t.fract()))
+    }
+}
+
+/// The player positions of the current attempt, sampled every fixed tick.
+#[derive(Resource, Default)]
+struct GhostRecorder {
+    path: Vec<[f32; 2]>,
+}
+
+#[derive(Component)]
+struct GhostRunner {
+    ghost: Ghost,
+    elapsed: f32,
+}
+
+fn reset_ghost_recorder(mut recorder: ResMut<GhostRecorder>) {
+    recorder.path.clear();
+}
+
+fn sample_player(
+    mut recorder: ResMut<GhostRecorder>,
+    player: Query<&Transform, With<PlayerMarker>>,
+) {
+    if let Ok(player) = player.single() {
+        recorder.path.push(player.translation.xy().into());
+    }
+}
+
+fn spawn_ghost(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    asset_server: Res<AssetServer>,
+    save: Res<SaveFile>,
+    difficulty: Res<Difficulty>,
+    current_level: Res<CurrentLevel>,
+) {
+    if !save.data.settings.show_ghost {
+        return;
+    }
+    let Some(ghost) = save.data.ghost(*difficulty, current_level.0) else {
+        return;
+    };
+    let Some(start) = ghost.position(0.0) else {
+        return;
+    };
+    let material = materials.add(ColorMaterial {
+        texture: Some(asset_server.load("sprites/goethe_paint_head.png")),
+        color: Color::WHITE.with_alpha(GHOST_ALPHA),
+        ..Default::default()
+    });
+    commands.spawn((
+        DespawnOnExit(LevelScreens::Level),
+        // just below the player
+        Transform::from_translation(start.extend(0.9)),
+        Mesh2d(meshes.add(Rectangle::new(20.0, 20.0))),
+        MeshMaterial2d(material),
+        GhostRunner {
+            ghost: ghost.clone(),
+            elapsed: 0.0,
+        },
+    ));
+}
+
+fn move_ghost(
+    mut ghosts: Query<(&mut GhostRunner, &mut Transform, &mut Visibility)>,
+    time: Res<Time>,
+) {
+    for (mut runner, mut transform, mut visibility) in &mut ghosts {
+        runner.elapsed += time.delta_secs();
+        match runner.ghost.position(runner.elapsed) {
+            Some(position) => {
+                transform.translation.x = position.x;
+                transform.translation.y = position.y;
+            }
+            None => *visibility = Visibility::Hidden,
+        }
+    }
+}
+
+/// Stores the finished attempt if it was the fastest, only attempts of clean runs are kept.
+fn keep_ghost(
+    opts: Res<Opts>,
+    recorder: Res<GhostRecorder>,
+    mut save: ResMut<SaveFile>,
+    assists: Res<Assists>,
+    run: Res<RunTimer>,
+    difficulty: Res<Difficulty>,
+    current_level: Res<CurrentLevel>,
+    time: Res<Time<Fixed>>,
+) {
+    if run.standing(&opts, &assists) != Standing::Clean || recorder.path.is_empty() {
+        return;
+    }
+    let ghost = Ghost {
+        interval: time.timestep().as_secs_f32(),
+        path: recorder.path.clone(),
+    };
+    if save.data.record_ghost(*difficulty, current_level.0, ghost) {
+        save.store();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::save::SaveData;
+
+    use super::*;
+
+    fn ghost(samples: usize) -> Ghost {
+        Ghost {
+            interval: 0.5,
+            path: (0..samples).map(|i| [i as f32 * 10.0, 0.0]).collect(),
+        }
+    }
+
+    #[test]
+    fn interpolates_between_samples() {
+        let ghost = ghost(3);
+        assert_eq!(ghost.duration(), 1.0);
+        assert_eq!(ghost.position(0.0), Some(Vec2::ZERO));
+        assert_eq!(ghost.position(0.75), Some(Vec2::new(15.0, 0.0)));
+        assert_eq!(ghost.position(1.0), Some(Vec2::new(20.0, 0.0)));
+        assert_eq!(ghost.position(1.5), None);
+    }
+
+    #[test]
+    fn keeps_the_fastest_attempt() {
+        let mut save = SaveData::default();
+        assert!(save.record_ghost(Difficulty::Wanderer, 1, ghost(10)));
+        assert!(!save.record_ghost(Difficulty::Wanderer, 1, ghost(12)));
+        assert!(save.record_ghost(Difficulty::Wanderer, 1, ghost(8)));
+        assert_eq!(save.ghost(Difficulty::Wanderer, 1), Some(&ghost(8)));
+        assert_eq!(save.ghost(Difficulty::Nightmare, 1), None);
+    }
+}
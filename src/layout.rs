@@ -0,0 +1,295 @@
+use std::{fmt, path::Path};
+
+use bevy::{
+    asset::RenderAssetUsages,
+    color::color_difference::EuclideanDistance,
+    image::{CompressedImageFormats, ImageSampler, ImageType},
+    prelude::*,
+};
+
+use crate::terrain::{Killzones, VoxelizedView};
+
+pub const TERRAIN_COLOR: &str = "#1A1C2C";
+pub const KILL_COLOR: &str = "#B13E53";
+pub const FINISH_COLOR: &str = "#73EFF7";
+pub const SPAWN_COLOR: &str = "#566C86";
+pub const ANCHOR_COLOR: &str = "#FFCD75";
+
+/// Only the first 3 bones are highlighted by the terrain shader.
+pub const SHADER_FINISHES: usize = 3;
+
+/// The parsed content of a level image, see [`crate::terrain::spawn_level`] for the palette.
+pub struct LevelLayout {
+    pub voxels: VoxelizedView,
+    pub killzones: Killzones,
+    pub finishes: Vec<(u32, u32)>,
+    pub spawns: Vec<(u32, u32)>,
+    /// Lanterns, see [`crate::anchor::TimeAnchor`].
+    pub anchors: Vec<(u32, u32)>,
+    /// Pixels that are neither transparent nor part of the palette.
+    pub unknown: Vec<(u32, u32)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LevelProblem {
+    WrongSize { width: u32, height: u32 },
+    UnknownColor { x: u32, y: u32, color: Srgba },
+    NoSpawn,
+    MultipleSpawns(Vec<(u32, u32)>),
+    NoFinish,
+    TooManyFinishes(usize),
+    FinishInTerrain { x: u32, y: u32 },
+}
+
+impl LevelProblem {
+    /// Warnings still produce a playable level, errors do not.
+    pub fn is_error(&self) -> bool {
+        !matches!(self, LevelProblem::TooManyFinishes(_))
+    }
+
+    pub fn severity(&self) -> &'static str {
+        if self.is_error() { "error" } else { "warning" }
+    }
+}
+
+impl fmt::Display for LevelProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelProblem::WrongSize { width, height } => {
+                write!(f, "levels must be 128x128 pixels, found {width}x{height}")
+            }
+            LevelProblem::UnknownColor { x, y, color } => write!(
+                f,
+                "pixel ({x}, {y}) has color {} which is not part of the palette",
+                color.to_hex()
+            ),
+            LevelProblem::NoSpawn => write!(f, "no spawn pixel ({SPAWN_COLOR})"),
+            LevelProblem::MultipleSpawns(spawns) => {
+                write!(f, "{} spawn pixels, only the last is used:", spawns.len())?;
+                for (x, y) in spawns {
+                    write!(f, " ({x}, {y})")?;
+                }
+                Ok(())
+            }
+            LevelProblem::NoFinish => {
+                write!(
+                    f,
+                    "no bone pixel ({FINISH_COLOR}), the level can not be finished"
+                )
+            }
+            LevelProblem::TooManyFinishes(n) => write!(
+                f,
+                "{n} bones, only the first {SHADER_FINISHES} are highlighted by the shader"
+            ),
+            LevelProblem::FinishInTerrain { x, y } => {
+                write!(f, "bone ({x}, {y}) is enclosed by terrain on all sides")
+            }
+        }
+    }
+}
+
+/// Loads a level image the same way the asset server does for `levels/*.png`.
+pub fn load_level_image(path: &Path) -> Result<Image, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("could not read: {e}"))?;
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("png");
+    Image::from_buffer(
+        &bytes,
+        ImageType::Extension(extension),
+        CompressedImageFormats::NONE,
+        true,
+        ImageSampler::Default,
+        RenderAssetUsages::all(),
+    )
+    .map_err(|e| format!("could not decode: {e}"))
+}
+
+impl LevelLayout {
+    pub fn parse(level: &Image) -> Result<LevelLayout, LevelProblem> {
+        if level.width() != 128 || level.height() != 128 {
+            return Err(LevelProblem::WrongSize {
+                width: level.width(),
+                height: level.height(),
+            });
+        }
+        let terrain = Color::Srgba(Srgba::hex(TERRAIN_COLOR).unwrap());
+        let kill = Color::Srgba(Srgba::hex(KILL_COLOR).unwrap());
+        let finish = Color::Srgba(Srgba::hex(FINISH_COLOR).unwrap());
+        let spawn_color = Color::Srgba(Srgba::hex(SPAWN_COLOR).unwrap());
+        let anchor = Color::Srgba(Srgba::hex(ANCHOR_COLOR).unwrap());
+
+        let mut layout = LevelLayout {
+            voxels: VoxelizedView::empty(),
+            killzones: Killzones::empty(),
+            finishes: Vec::new(),
+            spawns: Vec::new(),
+            anchors: Vec::new(),
+            unknown: Vec::new(),
+        };
+
+        for y in 0..level.height() {
+            for x in 0..level.width() {
+                if let Ok(color) = level.get_color_at(x, y) {
+                    let is_terrain = color.distance(&terrain) <= 0.0001;
+                    let is_kill = color.distance(&kill) <= 0.0001;
+                    let is_finish = color.distance(&finish) < 0.0001;
+                    let is_spawn = color.distance(&spawn_color) <= 0.0001;
+                    let is_anchor = color.distance(&anchor) <= 0.0001;
+                    layout.voxels.set(x, y, is_terrain);
+                    layout.killzones.set(x, y, is_kill);
+                    if is_finish {
+                        layout.finishes.push((x, y));
+                    }
+                    if is_spawn {
+                        layout.spawns.push((x, y));
+                    }
+                    if is_anchor {
+                        layout.anchors.push((x, y));
+                    }
+                    let known = is_terrain || is_kill || is_finish || is_spawn || is_anchor;
+                    if !known && color.alpha() > 0.0 {
+                        layout.unknown.push((x, y));
+                    }
+                }
+            }
+        }
+        layout.voxels.finish_coords = layout.finishes.clone();
+
+        Ok(layout)
+    }
+
+    /// The spawn used by the game, the last spawn pixel wins.
+    pub fn spawn(&self) -> Option<(u32, u32)> {
+        self.spawns.last().cloned()
+    }
+
+    pub fn problems(&self, level: &Image) -> Vec<LevelProblem> {
+        let mut problems = Vec::new();
+        for &(x, y) in &self.unknown {
+            let color = level.get_color_at(x, y).unwrap_or_default().to_srgba();
+            problems.push(LevelProblem::UnknownColor { x, y, color });
+        }
+        match self.spawns.len() {
+            0 => problems.push(LevelProblem::NoSpawn),
+            1 => (),
+            _ => problems.push(LevelProblem::MultipleSpawns(self.spawns.clone())),
+        }
+        if self.finishes.is_empty() {
+            problems.push(LevelProblem::NoFinish);
+        }
+        if self.finishes.len() > SHADER_FINISHES {
+            problems.push(LevelProblem::TooManyFinishes(self.finishes.len()));
+        }
+        for &(x, y) in &self.finishes {
+            let enclosed = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+                .iter()
+                .all(|(o_x, o_y)| self.voxels.get_checked(x as i32 + o_x, y as i32 + o_y));
+            if enclosed {
+                problems.push(LevelProblem::FinishInTerrain { x, y });
+            }
+        }
+        problems
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+
+    use super::*;
+
+    /// A transparent level with the given pixels painted.
+    fn level(pixels: &[(u32, u32, &str)]) -> Image {
+        let mut image = Image::new_fill(
+            Extent3d {
+                width: 128,
+                height: 128,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &[0; 4],
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::all(),
+        );
+        for &(x, y, color) in pixels {
+            image
+                .set_color_at(x, y, Color::Srgba(Srgba::hex(color).unwrap()))
+                .unwrap();
+        }
+        image
+    }
+
+    fn problems(pixels: &[(u32, u32, &str)]) -> Vec<LevelProblem> {
+        let image = level(pixels);
+        LevelLayout::parse(&image).unwrap().problems(&image)
+    }
+
+    #[test]
+    fn a_playable_level_has_no_problems() {
+        assert_eq!(
+            problems(&[(10, 10, SPAWN_COLOR), (100, 20, FINISH_COLOR)]),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn missing_spawn() {
+        let problems = problems(&[(100, 20, FINISH_COLOR)]);
+        assert_eq!(problems, vec![LevelProblem::NoSpawn]);
+        assert!(problems[0].is_error());
+    }
+
+    #[test]
+    fn several_spawns() {
+        let image = level(&[
+            (10, 10, SPAWN_COLOR),
+            (30, 40, SPAWN_COLOR),
+            (100, 20, FINISH_COLOR),
+        ]);
+        let layout = LevelLayout::parse(&image).unwrap();
+        let problems = layout.problems(&image);
+        assert_eq!(
+            problems,
+            vec![LevelProblem::MultipleSpawns(vec![(10, 10), (30, 40)])]
+        );
+        assert!(problems[0].is_error());
+        assert_eq!(layout.spawn(), Some((30, 40)));
+    }
+
+    #[test]
+    fn no_bones() {
+        let problems = problems(&[(10, 10, SPAWN_COLOR)]);
+        assert_eq!(problems, vec![LevelProblem::NoFinish]);
+        assert!(problems[0].is_error());
+    }
+
+    #[test]
+    fn too_many_bones_is_a_warning() {
+        let problems = problems(&[
+            (10, 10, SPAWN_COLOR),
+            (100, 20, FINISH_COLOR),
+            (100, 40, FINISH_COLOR),
+            (100, 60, FINISH_COLOR),
+            (100, 80, FINISH_COLOR),
+        ]);
+        assert_eq!(problems, vec![LevelProblem::TooManyFinishes(4)]);
+        assert!(!problems[0].is_error());
+    }
+
+    #[test]
+    fn unknown_palette_color() {
+        let problems = problems(&[
+            (10, 10, SPAWN_COLOR),
+            (100, 20, FINISH_COLOR),
+            (50, 60, "#FF00FF"),
+        ]);
+        assert!(
+            matches!(
+                problems.as_slice(),
+                [LevelProblem::UnknownColor { x: 50, y: 60, color }]
+                    if color.to_hex() == "#FF00FF"
+            ),
+            "{problems:?}"
+        );
+        assert!(problems[0].is_error());
+    }
+}
@@ -4,14 +4,25 @@ use bevy::{
     ui_widgets::{Activate, observe},
 };
 
-use crate::{RequiredAssets, gameplay::RunStartTime, screens::Screen, terrain::RequiredFinishes};
+use crate::{
+    Opts, RequiredAssets,
+    adaptive::AdaptiveDifficulty,
+    assist::Assists,
+    death::{DeathCause, PlayerDeath},
+    difficulty::Difficulty,
+    save::SaveFile,
+    screens::{Screen, go_to_main},
+    speedrun::{RunTimer, Standing, format_time},
+    terrain::RequiredFinishes,
+    transition::{Transition, TransitionStyle},
+};
 pub struct LevelPlugin;
 
 impl Plugin for LevelPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(CurrentLevel(0));
         app.insert_state(LevelScreens::None);
-        app.add_systems(OnEnter(Screen::Gameplay), level);
+        app.add_systems(OnEnter(Screen::Gameplay), (start_level, level));
         app.add_systems(OnEnter(LevelScreens::Restart), level);
         app.add_systems(OnEnter(LevelScreens::Intermission), spawn_intermission);
         app.add_systems(OnEnter(LevelScreens::Level), spawn_timer);
@@ -28,6 +39,10 @@ fn level(mut next: ResMut<NextState<LevelScreens>>) {
     next.set(LevelScreens::Level);
 }
 
+fn start_level(mut current_level: ResMut<CurrentLevel>, opts: Res<Opts>) {
+    current_level.0 = opts.start_level();
+}
+
 #[derive(Resource, Clone, Copy, Eq, PartialEq, Hash, Debug)]
 pub struct CurrentLevel(pub u32);
 
@@ -110,13 +125,17 @@ fn spawn_intermission(
 fn next_level(
     _: On<Activate>,
     mut current_level: ResMut<CurrentLevel>,
-    mut next: ResMut<NextState<LevelScreens>>,
+    mut transition: ResMut<Transition>,
 ) {
+    // a second click while the screen is covered would skip a level
+    if transition.is_running() {
+        return;
+    }
     current_level.0 += 1;
     if current_level.0 < 4 {
-        next.set(LevelScreens::Level);
+        transition.start(LevelScreens::Level, TransitionStyle::Ink);
     } else {
-        next.set(LevelScreens::GameEnd);
+        transition.start(LevelScreens::GameEnd, TransitionStyle::Ink);
     }
 }
 
@@ -161,11 +180,15 @@ Erreicht den Hof mit Mühe und Not;
 In seinen Armen das Kind war tot.";
 
 #[derive(Component)]
-struct PoemState {
-    timer: Timer,
+pub struct PoemState {
+    pub timer: Timer,
 }
 
-fn spawn_timer(mut commands: Commands, assets: Res<RequiredAssets>) {
+pub fn spawn_timer(
+    mut commands: Commands,
+    assets: Res<RequiredAssets>,
+    difficulty: Res<Difficulty>,
+) {
     commands.spawn((
         DespawnOnExit(LevelScreens::Level),
         Node {
@@ -189,7 +212,7 @@ fn spawn_timer(mut commands: Commands, assets: Res<RequiredAssets>) {
                 ..Default::default()
             },
             PoemState {
-                timer: Timer::from_seconds(90.0, TimerMode::Once)
+                timer: Timer::from_seconds(difficulty.poem_duration(), TimerMode::Once)
             },
             children![(
                 Text::new(POEM.replace("\n", " ")),
@@ -242,23 +265,63 @@ fn update_finish_text(
 fn update_timer(
     mut timer: Single<(&mut ScrollPosition, &mut PoemState, &ComputedNode)>,
     time: Res<Time>,
-    mut next: ResMut<NextState<LevelScreens>>,
+    mut deaths: MessageWriter<PlayerDeath>,
+    opts: Res<Opts>,
+    assists: Res<Assists>,
 ) {
+    if opts.no_timer || assists.infinite_timer {
+        return;
+    }
     timer.1.timer.tick(time.delta());
     if timer.1.timer.just_finished() {
-        next.set(LevelScreens::Restart);
+        deaths.write(PlayerDeath {
+            cause: DeathCause::TimeUp,
+        });
     }
 
     timer.0.x = timer.2.content_size.x * timer.1.timer.fraction();
 }
 
-fn display_end(
+pub fn display_end(
     mut commands: Commands,
-    start: Res<RunStartTime>,
+    run: Res<RunTimer>,
     assets: Res<RequiredAssets>,
-    time: Res<Time>,
+    adaptive: Res<AdaptiveDifficulty>,
+    difficulty: Res<Difficulty>,
+    mut save: ResMut<SaveFile>,
+    assists: Res<Assists>,
+    opts: Res<Opts>,
 ) {
-    let i = time.elapsed_secs() - start.0;
+    let i = run.elapsed;
+    let standing = run.standing(&opts, &assists);
+    let assisted = standing != Standing::Clean;
+    let previous = save.data.best_run(*difficulty, assisted).cloned();
+    let ranked = standing != Standing::Unranked;
+    let new_best = ranked && save.data.record_run(*difficulty, assisted, i, &run.splits);
+    if ranked {
+        save.store();
+    }
+    let mut text = format!("Difficulty: {}\n", difficulty.name());
+    if assists.any() {
+        text.push_str(&format!("Assists: {}\n", assists.describe()));
+    }
+    text.push_str(&format!("You woke up after: {}\n", format_time(i)));
+    match &previous {
+        _ if opts.practice => text.push_str("Practice runs do not count as best times.\n"),
+        _ if !ranked => text.push_str(
+            "Runs using cheats or the console, or not starting at the first level, do not count as best times.\n",
+        ),
+        Some(best) if !new_best => {
+            text.push_str(&format!("Best time: {}\n", format_time(best.time)))
+        }
+        _ => text.push_str("A new best time!\n"),
+    }
+    text.push('\n');
+    text.push_str(&run.table(previous.as_ref().map(|best| best.splits.as_slice())));
+    let eased = adaptive.summary();
+    if !eased.is_empty() {
+        text = format!("{text}\n\n{eased}");
+    }
     commands.spawn((
         DespawnOnExit(LevelScreens::GameEnd),
         Node {
@@ -280,7 +343,7 @@ fn display_end(
                 },
                 BackgroundColor(Color::srgb(0.15, 0.15, 0.15)),
                 children![(
-                    Text::new(format!("You woke up after: {i:?}s")),
+                    Text::new(text),
                     TextFont {
                         font: assets.font.clone().unwrap(),
                         ..Default::default()
@@ -304,13 +367,3 @@ fn display_end(
         ],
     ));
 }
-fn go_to_main(
-    _: On<Activate>,
-    mut current_level: ResMut<CurrentLevel>,
-    mut next: ResMut<NextState<LevelScreens>>,
-    mut next_main: ResMut<NextState<Screen>>,
-) {
-    current_level.0 = 0;
-    next.set(LevelScreens::None);
-    next_main.set(Screen::Main);
-}
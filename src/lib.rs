@@ -0,0 +1,211 @@
+use bevy::{
+    feathers::{FeathersPlugins, dark_theme::create_dark_theme, theme::UiTheme},
+    prelude::*,
+};
+use clap::Parser;
+use std::path::PathBuf;
+
+use crate::{
+    dev::DevPlugin,
+    difficulty::Difficulty,
+    gameplay::GameplayPlugin,
+    replay::{Recorder, Recording, Replay, replay_frame_time, replay_opts},
+    save::SaveFile,
+    screens::ScreenPlugin,
+    tools::Command,
+};
+
+mod adaptive;
+mod anchor;
+mod assist;
+mod death;
+mod dev;
+mod difficulty;
+mod gameplay;
+mod ghost;
+pub mod layout;
+mod levels;
+mod livesplit;
+mod main_screen;
+mod player;
+pub mod player_controller;
+mod practice;
+mod replay;
+mod rewind;
+mod save;
+mod screens;
+mod settings;
+mod snapshot;
+mod speedrun;
+mod telemetry;
+pub mod terrain;
+#[cfg(test)]
+mod test_support;
+mod tools;
+mod transition;
+
+/// Dornburg, a platformer through an ever shifting crypt.
+#[derive(Parser, Debug, Resource, Clone)]
+pub struct Opts {
+    #[arg(long)]
+    debug_colliders: bool,
+    /// Enable the debug overlay (F3), the time field heatmap (F4) and the developer console (`).
+    #[arg(long)]
+    dev: bool,
+    /// Start the game in this level (1-4).
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..=4))]
+    level: Option<u32>,
+    /// Shifts the noise driving the terrain, 0 is the noise the levels were designed with.
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+    /// The poem never runs out.
+    #[arg(long)]
+    no_timer: bool,
+    /// Touching lava or leaving the level does not restart it.
+    #[arg(long)]
+    god_mode: bool,
+    /// Fly through the terrain, W/S move up and down.
+    #[arg(long)]
+    noclip: bool,
+    /// Skip the main menu and the camera zoom.
+    #[arg(long)]
+    skip_intro: bool,
+    /// Start with the time field heatmap and terrain activity overlay (F4) enabled.
+    #[arg(long, requires = "dev")]
+    heatmap: bool,
+    /// Ease levels the player keeps failing: a wider bubble, slower terrain and a longer poem.
+    #[arg(long)]
+    adaptive: bool,
+    /// Starting difficulty, can be changed in the main menu.
+    #[arg(long, value_enum, default_value_t)]
+    difficulty: Difficulty,
+    /// Where best times are stored.
+    #[arg(long, default_value = "save.ron")]
+    save: PathBuf,
+    /// Export finished runs to this LiveSplit splits file (.lss).
+    #[arg(long)]
+    livesplit: Option<PathBuf>,
+    /// Keep the level, its time and whether the game is loading in this file, for autosplitters.
+    #[arg(long)]
+    autosplitter: Option<PathBuf>,
+    /// Record the inputs of each level attempt to this file, the last attempt is kept.
+    #[arg(long, conflicts_with = "replay")]
+    record: Option<PathBuf>,
+    /// Play back a file written by `--record`.
+    #[arg(long)]
+    replay: Option<PathBuf>,
+    /// Practice mode: F5 takes a savestate of the level, F9 restores it. Runs do not count.
+    #[arg(long)]
+    practice: bool,
+    /// Where practice mode keeps the savestate, so it survives restarting the game.
+    #[arg(long, default_value = "savestate.ron")]
+    savestate: PathBuf,
+    /// Where level attempts are logged once telemetry is enabled in the settings.
+    #[arg(long, default_value = "telemetry.ron")]
+    telemetry: PathBuf,
+    /// Window size as `WxH`.
+    #[arg(long, value_parser = parse_window_size)]
+    windowed: Option<UVec2>,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+impl Opts {
+    /// Index into [`RequiredAssets::levels`] of the first level of a run.
+    fn start_level(&self) -> u32 {
+        self.level.map_or(0, |level| level - 1)
+    }
+}
+
+fn parse_window_size(s: &str) -> Result<UVec2, String> {
+    let (w, h) = s.split_once('x').ok_or("expected `WxH`")?;
+    let w: u32 = w.parse().map_err(|e| format!("{w}: {e}"))?;
+    let h: u32 = h.parse().map_err(|e| format!("{h}: {e}"))?;
+    Ok(UVec2::new(w, h))
+}
+
+/// Runs the game, or one of the tools if a subcommand was given.
+pub fn run() -> AppExit {
+    let mut opts = Opts::parse();
+    if let Some(command) = opts.command.take() {
+        return tools::run(command);
+    }
+    let replay = match &opts.replay {
+        Some(path) => match Recording::load(path) {
+            Ok(recording) => Some(recording),
+            Err(e) => {
+                eprintln!("{e}");
+                return AppExit::error();
+            }
+        },
+        None => None,
+    };
+    if let Some(recording) = &replay {
+        replay_opts(&mut opts, recording);
+    }
+    if opts.record.is_some() || replay.is_some() {
+        // The relief depends on earlier attempts, which a recording does not hold.
+        opts.adaptive = false;
+    }
+    let mut window = Window::default();
+    if let Some(size) = opts.windowed {
+        window.resolution = size.into();
+    }
+    let mut app = App::new();
+    if let Some(path) = &opts.record {
+        app.insert_resource(Recorder {
+            path: path.clone(),
+            recording: None,
+        });
+    }
+    if let Some(recording) = replay {
+        replay_frame_time(&mut app, &recording);
+        app.insert_resource(Replay::new(recording));
+    }
+    app.insert_resource(opts.clone())
+        .insert_resource(opts.difficulty)
+        .insert_resource(SaveFile::load(&opts.save))
+        .insert_resource(ClearColor(Color::srgb(0.0, 0.0, 0.0)))
+        .insert_resource(UiTheme(create_dark_theme()))
+        .insert_resource(RequiredAssets {
+            levels: Vec::new(),
+            font: None,
+        })
+        .add_systems(Startup, load_levels)
+        .add_plugins((
+            DefaultPlugins.set(WindowPlugin {
+                primary_window: Some(window),
+                ..Default::default()
+            }),
+            FeathersPlugins,
+            ScreenPlugin,
+            GameplayPlugin { opts: opts.clone() },
+        ));
+    if opts.dev {
+        app.add_plugins(DevPlugin);
+    }
+    app.run()
+}
+
+#[derive(Resource)]
+pub struct RequiredAssets {
+    pub levels: Vec<Handle<Image>>,
+    font: Option<Handle<Font>>,
+}
+
+fn load_levels(asset_server: Res<AssetServer>, mut required: ResMut<RequiredAssets>) {
+    required
+        .levels
+        .push(asset_server.load("levels/level_1.png"));
+    required
+        .levels
+        .push(asset_server.load("levels/level_2.png"));
+    required
+        .levels
+        .push(asset_server.load("levels/level_3.png"));
+    required
+        .levels
+        .push(asset_server.load("levels/level_4.png"));
+
+    required.font = Some(asset_server.load("fonts/CinzelDecorative-Regular.ttf"));
+}
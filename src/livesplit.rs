@@ -0,0 +1,525 @@
+use std::io::ErrorKind;
+
+use bevy::prelude::*;
+
+use crate::{
+    Opts,
+    assist::Assists,
+    difficulty::Difficulty,
+    levels::{CurrentLevel, LevelScreens, display_end},
+    save::SaveFile,
+    screens::Screen,
+    speedrun::{RunTimer, Split, Standing},
+};
+
+/// Seconds between two writes of the autosplitter state file while nothing else changes.
+const STATE_INTERVAL: f32 = 0.1;
+
+/// Exports finished runs to LiveSplit (`--livesplit`) and keeps a state file for autosplitters
+/// up to date (`--autosplitter`).
+pub struct LiveSplitPlugin;
+
+impl Plugin for LiveSplitPlugin {
+    fn build(&self, app: &mut App) {
+        let autosplitter = |opts: Res<Opts>| opts.autosplitter.is_some();
+        app.insert_resource(StateFileTimer(Timer::from_seconds(
+            STATE_INTERVAL,
+            TimerMode::Repeating,
+        )));
+        app.add_systems(
+            OnEnter(LevelScreens::GameEnd),
+            export_splits
+                .after(display_end)
+                .run_if(|opts: Res<Opts>| opts.livesplit.is_some()),
+        );
+        for screen in [
+            LevelScreens::Level,
+            LevelScreens::Restart,
+            LevelScreens::Intermission,
+            LevelScreens::GameEnd,
+        ] {
+            app.add_systems(OnEnter(screen), write_state.run_if(autosplitter));
+        }
+        app.add_systems(
+            Update,
+            write_state
+                .run_if(in_state(Screen::Gameplay).and(autosplitter).and(state_due))
+                .after(tick_state_timer),
+        );
+        app.add_systems(Update, tick_state_timer.run_if(autosplitter));
+    }
+}
+
+#[derive(Resource)]
+struct StateFileTimer(Timer);
+
+fn tick_state_timer(mut timer: ResMut<StateFileTimer>, time: Res<Time<Real>>) {
+    timer.0.tick(time.delta());
+}
+
+fn state_due(timer: Res<StateFileTimer>) -> bool {
+    timer.0.just_finished()
+}
+
+/// `key=value` lines, see [`write_state`].
+pub fn autosplitter_state(
+    level: u32,
+    screen: LevelScreens,
+    run: &RunTimer,
+    difficulty: Difficulty,
+) -> String {
+    let level_start = run.splits.last().map_or(0.0, |split| split.time);
+    let (state, loading) = match screen {
+        LevelScreens::Level => ("level", false),
+        LevelScreens::Restart => ("restart", true),
+        LevelScreens::Intermission => ("intermission", true),
+        LevelScreens::GameEnd => ("end", true),
+        LevelScreens::None => ("menu", true),
+    };
+    format!(
+        "level={}\nstate={state}\nloading={loading}\nlevel_time={:.3}\nrun_time={:.3}\nsplits={}\ndifficulty={}\n",
+        level + 1,
+        run.elapsed - level_start,
+        run.elapsed,
+        run.splits.len(),
+        difficulty.name(),
+    )
+}
+
+/// Replaces the state file, so an autosplitter polling it never reads half of it.
+fn write_state(
+    opts: Res<Opts>,
+    current_level: Res<CurrentLevel>,
+    screen: Res<State<LevelScreens>>,
+    run: Res<RunTimer>,
+    difficulty: Res<Difficulty>,
+) {
+    let Some(path) = &opts.autosplitter else {
+        return;
+    };
+    let content = autosplitter_state(current_level.0, *screen.get(), &run, *difficulty);
+    let partial = path.with_extension("partial");
+    if let Err(e) = std::fs::write(&partial, content).and_then(|_| std::fs::rename(&partial, path))
+    {
+        warn!("{}: {e}", path.display());
+    }
+}
+
+/// Adds the run to the splits file, the attempts already in it are kept.
+fn export_splits(
+    opts: Res<Opts>,
+    run: Res<RunTimer>,
+    save: Res<SaveFile>,
+    difficulty: Res<Difficulty>,
+    assists: Res<Assists>,
+) {
+    let Some(path) = &opts.livesplit else {
+        return;
+    };
+    let assisted = match run.standing(&opts, &assists) {
+        Standing::Clean => false,
+        Standing::Assisted => true,
+        Standing::Unranked => return,
+    };
+    let best = save
+        .data
+        .best_run(*difficulty, assisted)
+        .map_or(run.splits.as_slice(), |best| best.splits.as_slice());
+    let mut category = format!("Any% - {}", difficulty.name());
+    if assisted {
+        category.push_str(" (assisted)");
+    }
+    let history = match std::fs::read_to_string(path) {
+        Ok(content) => History::parse(&content, &category),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(History::default()),
+        Err(e) => Err(e.to_string()),
+    };
+    let history = match history {
+        Ok(history) => history,
+        Err(e) => {
+            warn!("{}: {e}, the run is not exported", path.display());
+            return;
+        }
+    };
+    if let Err(e) = std::fs::write(path, lss(&category, best, &run.splits, &history)) {
+        warn!("{}: {e}", path.display());
+    }
+}
+
+/// The attempts an existing splits file holds, see [`History::parse`].
+#[derive(Default, Debug, PartialEq)]
+pub struct History {
+    /// LiveSplit also counts attempts that were reset before the end.
+    pub attempt_count: u32,
+    /// `(id, time)` of the attempts that reached the end.
+    pub attempts: Vec<(u32, f32)>,
+    pub segments: Vec<SegmentHistory>,
+}
+
+#[derive(Default, Debug, PartialEq)]
+pub struct SegmentHistory {
+    /// The fastest the segment was ever done in.
+    pub best: Option<f32>,
+    /// `(attempt id, time)` of every attempt that finished the segment.
+    pub times: Vec<(u32, f32)>,
+}
+
+impl History {
+    /// Reads the attempts of a splits file written by [`lss`] or LiveSplit, fails if it holds
+    /// the splits of another category.
+    pub fn parse(content: &str, category: &str) -> Result<History, String> {
+        let name = elements(content, "CategoryName")
+            .first()
+            .map_or("", |(_, name)| name.trim());
+        if name != category {
+            return Err(format!("holds the splits of {name:?}, not {category:?}"));
+        }
+        let attempt_count = elements(content, "AttemptCount")
+            .first()
+            .map_or(Ok(0), |(_, count)| count.trim().parse::<u32>())
+            .map_err(|e| format!("AttemptCount: {e}"))?;
+        let timed = |(attributes, inner): (&str, &str)| -> Result<Option<(u32, f32)>, String> {
+            let Some(time) = elements(inner, "RealTime").first().map(|(_, time)| *time) else {
+                return Ok(None);
+            };
+            Ok(Some((id(attributes)?, parse_lss_time(time)?)))
+        };
+        let attempts = elements(content, "Attempt")
+            .into_iter()
+            .filter_map(|element| timed(element).transpose())
+            .collect::<Result<_, _>>()?;
+        let segments = elements(content, "Segment")
+            .into_iter()
+            .map(|(_, segment)| {
+                let best = match elements(segment, "BestSegmentTime").first() {
+                    Some((_, best)) => match elements(best, "RealTime").first() {
+                        Some((_, time)) => Some(parse_lss_time(time)?),
+                        None => None,
+                    },
+                    None => None,
+                };
+                let times = elements(segment, "Time")
+                    .into_iter()
+                    .filter_map(|element| timed(element).transpose())
+                    .collect::<Result<_, _>>()?;
+                Ok(SegmentHistory { best, times })
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(History {
+            attempt_count,
+            attempts,
+            segments,
+        })
+    }
+}
+
+/// `(attributes, content)` of every `<tag>` element in `content`, the format is simple enough
+/// that elements of one tag are never nested.
+fn elements<'a>(content: &'a str, tag: &str) -> Vec<(&'a str, &'a str)> {
+    let open = format!("<{tag}");
+    let close = format!("</{tag}>");
+    let mut found = Vec::new();
+    let mut rest = content;
+    while let Some(start) = rest.find(&open) {
+        rest = &rest[start + open.len()..];
+        // `<Attempt` also starts `<AttemptCount>`
+        if !rest.starts_with([' ', '>', '/']) {
+            continue;
+        }
+        let Some(end) = rest.find('>') else {
+            break;
+        };
+        let attributes = &rest[..end];
+        rest = &rest[end + 1..];
+        if let Some(attributes) = attributes.strip_suffix('/') {
+            found.push((attributes, ""));
+        } else if let Some(end) = rest.find(&close) {
+            found.push((attributes, &rest[..end]));
+            rest = &rest[end + close.len()..];
+        }
+    }
+    found
+}
+
+fn id(attributes: &str) -> Result<u32, String> {
+    attributes
+        .split_once("id=\"")
+        .and_then(|(_, rest)| rest.split_once('"'))
+        .ok_or("an attempt without an id")?
+        .0
+        .parse()
+        .map_err(|e| format!("attempt id: {e}"))
+}
+
+/// Reads [`lss_time`], LiveSplit prefixes times over a day with the days.
+fn parse_lss_time(time: &str) -> Result<f32, String> {
+    let parse = |part: &str| {
+        part.parse::<f64>()
+            .map_err(|e| format!("{}: {e}", time.trim()))
+    };
+    let mut parts = time.trim().rsplitn(3, ':');
+    let seconds = parse(parts.next().unwrap_or_default())?;
+    let minutes = parse(parts.next().ok_or("expected hh:mm:ss")?)?;
+    let hours = parts.next().ok_or("expected hh:mm:ss")?;
+    let hours = match hours.split_once('.') {
+        Some((days, hours)) => parse(days)? * 24.0 + parse(hours)?,
+        None => parse(hours)?,
+    };
+    Ok((hours * 3600.0 + minutes * 60.0 + seconds) as f32)
+}
+
+/// A LiveSplit splits file with `best` as the personal best and `run` added to the attempts
+/// in `history`, the best segments are the fastest of all of them.
+pub fn lss(category: &str, best: &[Split], run: &[Split], history: &History) -> String {
+    let earlier_ids = history
+        .attempts
+        .iter()
+        .chain(history.segments.iter().flat_map(|segment| &segment.times))
+        .map(|(id, _)| *id);
+    let id = earlier_ids.fold(history.attempt_count, u32::max) + 1;
+    let time = |id: u32, time: f32| {
+        format!(
+            "\n        <Time id=\"{id}\">\n          <RealTime>{}</RealTime>\n        </Time>",
+            lss_time(time)
+        )
+    };
+    let mut segments = String::new();
+    let mut best_previous = 0.0;
+    let mut run_previous = 0.0;
+    let no_history = SegmentHistory::default();
+    for (i, best_split) in best.iter().enumerate() {
+        let earlier = history.segments.get(i).unwrap_or(&no_history);
+        let best_segment = best_split.time - best_previous;
+        let run_segment = run.get(i).map(|split| split.time - run_previous);
+        let gold = [Some(best_segment), run_segment, earlier.best]
+            .into_iter()
+            .flatten()
+            .fold(f32::INFINITY, f32::min);
+        let mut times: String = earlier.times.iter().map(|&(id, t)| time(id, t)).collect();
+        if let Some(segment) = run_segment {
+            times.push_str(&time(id, segment));
+        }
+        if !times.is_empty() {
+            times.push_str("\n      ");
+        }
+        segments.push_str(&format!(
+            "    <Segment>
+      <Name>Level {}</Name>
+      <Icon />
+      <SplitTimes>
+        <SplitTime name=\"Personal Best\">
+          <RealTime>{}</RealTime>
+        </SplitTime>
+      </SplitTimes>
+      <BestSegmentTime>
+        <RealTime>{}</RealTime>
+      </BestSegmentTime>
+      <SegmentHistory>{times}</SegmentHistory>
+    </Segment>
+",
+            best_split.level + 1,
+            lss_time(best_split.time),
+            lss_time(gold),
+        ));
+        best_previous = best_split.time;
+        if let Some(split) = run.get(i) {
+            run_previous = split.time;
+        }
+    }
+    let attempt = |id: u32, time: f32| {
+        format!(
+            "\n    <Attempt id=\"{id}\">\n      <RealTime>{}</RealTime>\n    </Attempt>",
+            lss_time(time)
+        )
+    };
+    let mut attempts: String = history
+        .attempts
+        .iter()
+        .map(|&(id, time)| attempt(id, time))
+        .collect();
+    if let Some(last) = run.last() {
+        attempts.push_str(&attempt(id, last.time));
+    }
+    if !attempts.is_empty() {
+        attempts.push_str("\n  ");
+    }
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<Run version=\"1.7.0\">
+  <GameIcon />
+  <GameName>Dornburg</GameName>
+  <CategoryName>{category}</CategoryName>
+  <Offset>00:00:00</Offset>
+  <AttemptCount>{}</AttemptCount>
+  <AttemptHistory>{attempts}</AttemptHistory>
+  <Segments>
+{segments}  </Segments>
+  <AutoSplitterSettings />
+</Run>
+",
+        history.attempt_count + 1
+    )
+}
+
+/// `hh:mm:ss.fffffff`, the time format of LiveSplit.
+fn lss_time(seconds: f32) -> String {
+    let micros = (seconds.max(0.0) as f64 * 1_000_000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}.{:06}0",
+        micros / 3_600_000_000,
+        micros / 60_000_000 % 60,
+        micros / 1_000_000 % 60,
+        micros % 1_000_000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::state::app::StatesPlugin;
+    use clap::Parser;
+
+    use crate::{save::SaveData, test_support::TempDir};
+
+    use super::*;
+
+    fn splits(times: &[f32]) -> Vec<Split> {
+        times
+            .iter()
+            .enumerate()
+            .map(|(level, &time)| Split {
+                level: level as u32,
+                time,
+                deaths: 0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn formats_livesplit_times() {
+        assert_eq!(lss_time(0.0), "00:00:00.0000000");
+        assert_eq!(lss_time(83.5), "00:01:23.5000000");
+        assert_eq!(lss_time(3725.25), "01:02:05.2500000");
+    }
+
+    #[test]
+    fn segments_hold_the_best_run_and_the_attempt() {
+        let content = lss(
+            "Any% - Wanderer",
+            &splits(&[30.0, 70.0]),
+            &splits(&[35.0, 60.0]),
+            &History::default(),
+        );
+        assert_eq!(content.matches("<Segment>").count(), 2);
+        assert!(content.contains("<CategoryName>Any% - Wanderer</CategoryName>"));
+        // the second level took 25s in the attempt, faster than the 40s of the best run
+        assert!(
+            content.contains("<BestSegmentTime>\n        <RealTime>00:00:25.0000000</RealTime>")
+        );
+        assert!(content.contains("<RealTime>00:01:10.0000000</RealTime>"));
+        assert!(content.contains("<Attempt id=\"1\">\n      <RealTime>00:01:00.0000000"));
+    }
+
+    #[test]
+    fn later_runs_are_added_to_the_history() {
+        let category = "Any% - Wanderer";
+        let best = splits(&[30.0, 70.0]);
+        let first = lss(category, &best, &splits(&[35.0, 60.0]), &History::default());
+        let history = History::parse(&first, category).unwrap();
+        assert_eq!(
+            history,
+            History {
+                attempt_count: 1,
+                attempts: vec![(1, 60.0)],
+                segments: vec![
+                    SegmentHistory {
+                        best: Some(30.0),
+                        times: vec![(1, 35.0)],
+                    },
+                    SegmentHistory {
+                        best: Some(25.0),
+                        times: vec![(1, 25.0)],
+                    },
+                ],
+            }
+        );
+
+        // slower than both, the best segments stay
+        let second = lss(category, &best, &splits(&[40.0, 80.0]), &history);
+        let history = History::parse(&second, category).unwrap();
+        assert_eq!(history.attempt_count, 2);
+        assert_eq!(history.attempts, vec![(1, 60.0), (2, 80.0)]);
+        assert_eq!(history.segments[0].best, Some(30.0));
+        assert_eq!(history.segments[1].best, Some(25.0));
+        assert_eq!(history.segments[1].times, vec![(1, 25.0), (2, 40.0)]);
+
+        assert!(History::parse(&second, "Any% - Nightmare").is_err());
+    }
+
+    #[test]
+    fn reads_livesplit_times() {
+        assert_eq!(parse_lss_time("00:01:23.5000000"), Ok(83.5));
+        assert_eq!(parse_lss_time("1.02:00:00"), Ok(93_600.0));
+        assert!(parse_lss_time("83.5").is_err());
+    }
+
+    /// Runs the plugin without a window and checks the files it writes along a run.
+    #[test]
+    fn writes_files_in_a_headless_run() {
+        let dir = TempDir::new("livesplit");
+        let state_path = dir.join("state.txt");
+        let lss_path = dir.join("run.lss");
+        let opts = Opts::parse_from([
+            "dornburg".as_ref(),
+            "--autosplitter".as_ref(),
+            state_path.as_os_str(),
+            "--livesplit".as_ref(),
+            lss_path.as_os_str(),
+        ]);
+
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin, LiveSplitPlugin))
+            .insert_resource(opts)
+            .insert_resource(CurrentLevel(0))
+            .insert_resource(Difficulty::Wanderer)
+            .init_resource::<Assists>()
+            .insert_resource(SaveFile {
+                path: dir.join("save.ron"),
+                data: SaveData::default(),
+            })
+            .init_resource::<RunTimer>()
+            .init_state::<Screen>()
+            .insert_state(LevelScreens::None);
+
+        let go_to = |app: &mut App, screen: LevelScreens| {
+            app.world_mut()
+                .resource_mut::<NextState<LevelScreens>>()
+                .set(screen);
+            app.update();
+        };
+        app.world_mut()
+            .resource_mut::<NextState<Screen>>()
+            .set(Screen::Gameplay);
+        go_to(&mut app, LevelScreens::Level);
+        let state = std::fs::read_to_string(&state_path).unwrap();
+        assert!(state.contains("level=1\nstate=level\nloading=false\n"));
+
+        app.world_mut().resource_mut::<RunTimer>().elapsed = 12.5;
+        go_to(&mut app, LevelScreens::Intermission);
+        let state = std::fs::read_to_string(&state_path).unwrap();
+        assert!(state.contains("state=intermission\nloading=true\n"));
+        assert!(state.contains("run_time=12.500\n"));
+
+        app.world_mut().resource_mut::<RunTimer>().splits = splits(&[12.5, 30.0]);
+        go_to(&mut app, LevelScreens::GameEnd);
+        let content = std::fs::read_to_string(&lss_path).unwrap();
+        assert_eq!(content.matches("<Segment>").count(), 2);
+        assert!(content.contains("<RealTime>00:00:30.0000000</RealTime>"));
+
+        // a second run is added to the file
+        go_to(&mut app, LevelScreens::Level);
+        go_to(&mut app, LevelScreens::GameEnd);
+        let content = std::fs::read_to_string(&lss_path).unwrap();
+        assert!(content.contains("<AttemptCount>2</AttemptCount>"));
+        assert_eq!(content.matches("<Attempt id=").count(), 2);
+    }
+}
@@ -8,7 +8,12 @@ use bevy::{
     ui_widgets::{Activate, observe},
 };
 
-use crate::{RequiredAssets, screens::Screen};
+use crate::{
+    Opts, RequiredAssets,
+    difficulty::Difficulty,
+    screens::Screen,
+    transition::{Transition, TransitionStyle},
+};
 pub struct MainScreenPlugin;
 
 #[derive(Component)]
@@ -23,19 +28,26 @@ impl Plugin for MainScreenPlugin {
         app.add_systems(Startup, setup_camera);
         app.add_systems(OnEnter(Screen::Main), setup_ui);
         app.add_systems(OnEnter(Screen::Help), setup_help);
-        app.add_systems(Update, handle_escape_help.run_if(in_state(Screen::Help)));
+        app.add_systems(
+            Update,
+            update_difficulty_label.run_if(in_state(Screen::Main)),
+        );
+        app.add_systems(
+            Update,
+            skip_main.run_if(in_state(Screen::Main).and(|opts: Res<Opts>| opts.skip_intro)),
+        );
     }
 }
 
-fn setup_camera(mut commands: Commands) {
-    commands.spawn((
-        Camera2d,
-        CameraIntro {
+fn setup_camera(mut commands: Commands, opts: Res<Opts>) {
+    let mut camera = commands.spawn(Camera2d);
+    if !opts.skip_intro {
+        camera.insert(CameraIntro {
             timer: Timer::from_seconds(15.0, TimerMode::Once),
             start_scale: 0.1,
             end_scale: 1.0,
-        },
-    ));
+        });
+    }
 }
 
 pub fn camera_intro_zoom(
@@ -63,8 +75,11 @@ fn setup_ui(mut commands: Commands) {
     commands.spawn(main_root());
 }
 
-/// 3 Buttons:
+/// 6 Buttons:
 /// * Play
+/// * Difficulty, cycles through the presets
+/// * Assist
+/// * Settings
 /// * Help
 /// * Quit
 fn main_root() -> impl Bundle {
@@ -84,6 +99,22 @@ fn main_root() -> impl Bundle {
                 button(ButtonProps::default(), (), Spawn(Text::new("Play!"))),
                 observe(go_to_play),
             ),
+            (
+                button(
+                    ButtonProps::default(),
+                    (),
+                    Spawn((Text::new(""), DifficultyLabel))
+                ),
+                observe(cycle_difficulty),
+            ),
+            (
+                button(ButtonProps::default(), (), Spawn(Text::new("Assist"))),
+                observe(go_to_assist),
+            ),
+            (
+                button(ButtonProps::default(), (), Spawn(Text::new("Settings"))),
+                observe(go_to_settings),
+            ),
             (
                 button(ButtonProps::default(), (), Spawn(Text::new("Help"))),
                 observe(go_to_help),
@@ -96,17 +127,50 @@ fn main_root() -> impl Bundle {
     )
 }
 
-fn go_to_help(_: On<Activate>, mut next: ResMut<NextState<Screen>>) {
-    next.set(Screen::Help);
+#[derive(Component)]
+struct DifficultyLabel;
+
+fn cycle_difficulty(_: On<Activate>, mut difficulty: ResMut<Difficulty>) {
+    *difficulty = difficulty.next();
+}
+
+fn update_difficulty_label(
+    difficulty: Res<Difficulty>,
+    mut label: Single<&mut Text, With<DifficultyLabel>>,
+) {
+    let text = format!("Difficulty: {}", difficulty.name());
+    if label.0 != text {
+        label.0 = text;
+    }
+}
+
+fn go_to_assist(_: On<Activate>, mut transition: ResMut<Transition>) {
+    transition.start(Screen::Assist, TransitionStyle::Fade);
+}
+
+fn go_to_settings(_: On<Activate>, mut transition: ResMut<Transition>) {
+    transition.start(Screen::Settings, TransitionStyle::Fade);
+}
+
+fn go_to_help(_: On<Activate>, mut transition: ResMut<Transition>) {
+    transition.start(Screen::Help, TransitionStyle::Fade);
 }
 
 fn go_to_play(
     _: On<Activate>,
-    mut next: ResMut<NextState<Screen>>,
+    mut transition: ResMut<Transition>,
     required: Res<RequiredAssets>,
     asset_server: Res<AssetServer>,
 ) {
-    if required
+    if required_loaded(&required, &asset_server) {
+        transition.start(Screen::Gameplay, TransitionStyle::Ink);
+    } else {
+        warn!("Not all required levels loaded try again soon");
+    }
+}
+
+fn required_loaded(required: &RequiredAssets, asset_server: &AssetServer) -> bool {
+    required
         .levels
         .iter()
         .all(|l| asset_server.is_loaded_with_dependencies(l.id()))
@@ -114,10 +178,18 @@ fn go_to_play(
             .font
             .clone()
             .is_some_and(|v| asset_server.is_loaded_with_dependencies(v.id()))
-    {
+}
+
+/// Starts the game as soon as everything is loaded, only once so the menu is reachable afterwards.
+fn skip_main(
+    mut skipped: Local<bool>,
+    mut next: ResMut<NextState<Screen>>,
+    required: Res<RequiredAssets>,
+    asset_server: Res<AssetServer>,
+) {
+    if !*skipped && required_loaded(&required, &asset_server) {
+        *skipped = true;
         next.set(Screen::Gameplay);
-    } else {
-        warn!("Not all required levels loaded try again soon");
     }
 }
 
@@ -134,7 +206,7 @@ fn setup_help(mut commands: Commands) {
         },
         ThemeBackgroundColor(tokens::WINDOW_BG),
         children![
-            Text::new("In this little platformer, you collect a number of bones per level.\nIf you touch the 'Lava', go out of bounds, or the timer runs out, the level starts again.\nYou control the player with:\nA/ArrowLeft: move left\nD/ArrowRight: move right\nSpace: jump\n\nThere are no limits to movement in the air. Go through the levels and enjoy this fever dream.\n\nGo back to the main menu by pressing ESC from here.")
+            Text::new("In this little platformer, you collect a number of bones per level.\nIf you touch the 'Lava', go out of bounds, or the timer runs out, the level starts again.\nYou control the player with:\nA/ArrowLeft: move left\nD/ArrowRight: move right\nSpace: jump\nE: pick up or place a lantern, it holds the crypt still around it for a while\nR (hold): turn back the changes of the crypt, 3 times per level\nT: show or hide the run timer\n\nThere are no limits to movement in the air. Go through the levels and enjoy this fever dream.\n\nGo back to the main menu by pressing ESC from here.")
         ],
     ));
 }
@@ -142,9 +214,3 @@ fn setup_help(mut commands: Commands) {
 fn quit(_: On<Activate>, mut commands: Commands) {
     commands.write_message(AppExit::Success);
 }
-
-pub fn handle_escape_help(keys: Res<ButtonInput<KeyCode>>, mut next: ResMut<NextState<Screen>>) {
-    if keys.just_pressed(KeyCode::Escape) {
-        next.set(Screen::Main);
-    }
-}
@@ -2,6 +2,7 @@ use avian2d::dynamics::rigid_body::Friction;
 use avian2d::prelude::*;
 use bevy::prelude::*;
 
+use crate::Opts;
 use crate::levels::LevelScreens;
 use crate::terrain::SpawnMarker;
 
@@ -14,6 +15,7 @@ pub fn spawn_player(
     mut materials: ResMut<Assets<ColorMaterial>>,
     spawn: Single<&Transform, With<SpawnMarker>>,
     asset_server: Res<AssetServer>,
+    opts: Res<Opts>,
 ) {
     let mut transform = *spawn.into_inner();
     transform.translation.z = 1.0;
@@ -25,17 +27,28 @@ pub fn spawn_player(
         uv_transform: Default::default(),
     });
 
-    commands.spawn((
+    let mut player = commands.spawn((
         DespawnOnExit(LevelScreens::Level),
         transform,
         Mesh2d(meshes.add(Rectangle::new(20.0, 20.0))),
         MeshMaterial2d(material),
+        player_body(),
+    ));
+    if opts.noclip {
+        // Sensors still collect bones, but pass through the terrain.
+        player.insert((Sensor, GravityScale(0.0)));
+    }
+}
+
+/// The physics of the player, without anything that needs a window.
+pub fn player_body() -> impl Bundle {
+    (
         Collider::rectangle(20.0, 20.0),
         RigidBody::Dynamic,
         Mass(1.0),
         Friction::new(0.3),
         PlayerMarker,
-    ));
+    )
 }
 
 pub fn sync_camera_to_player(
@@ -1,27 +1,44 @@
+use crate::Opts;
+use crate::difficulty::Difficulty;
 use crate::player::PlayerMarker;
 use avian2d::prelude::LinearVelocity;
 use bevy::input::ButtonInput;
 use bevy::prelude::{KeyCode, Query, Res, With};
 
+/// Vertical velocity set by a jump on [`Difficulty::Wanderer`], jumps can be repeated in the air.
+pub const JUMP_VELOCITY: f32 = 250.0;
+pub const MAX_HORIZONTAL_VELOCITY: f32 = 300.0;
+
 pub fn update_player_position(
     query: Query<&mut LinearVelocity, With<PlayerMarker>>,
     keys: Res<ButtonInput<KeyCode>>,
+    opts: Res<Opts>,
+    difficulty: Res<Difficulty>,
 ) {
-    // let up_key: KeyCode = KeyCode::KeyW; // unused as of now
+    // only used with --noclip
+    let up_key: KeyCode = KeyCode::KeyW;
     let left_key: KeyCode = KeyCode::KeyA;
     let right_key: KeyCode = KeyCode::KeyD;
     let jump_key: KeyCode = KeyCode::Space;
-    // let down_key: KeyCode = KeyCode::KeyS; // unused as of now
+    let down_key: KeyCode = KeyCode::KeyS;
 
     let directional_change_base = 5.0;
     let directional_change_threshold = 20.0;
     let directional_change_multiplier = 10.0;
 
-    let max_horizontal_velocity = 300.0;
+    let max_horizontal_velocity = MAX_HORIZONTAL_VELOCITY;
 
     for mut linear_velocity in query {
-        if keys.just_pressed(jump_key) {
-            linear_velocity.y = 250.0;
+        if opts.noclip {
+            linear_velocity.y = 0.0;
+            if keys.pressed(up_key) || keys.pressed(KeyCode::ArrowUp) {
+                linear_velocity.y += max_horizontal_velocity;
+            }
+            if keys.pressed(down_key) || keys.pressed(KeyCode::ArrowDown) {
+                linear_velocity.y -= max_horizontal_velocity;
+            }
+        } else if keys.just_pressed(jump_key) {
+            linear_velocity.y = difficulty.jump_velocity();
         }
         if keys.pressed(left_key) || keys.pressed(KeyCode::ArrowLeft) {
             if linear_velocity.x > directional_change_threshold {
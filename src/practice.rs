@@ -0,0 +1,288 @@
+use std::{path::Path, time::Duration};
+
+use avian2d::prelude::{AngularVelocity, LinearVelocity};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Opts,
+    anchor::{AnchorState, Carried, TimeAnchor},
+    dev::console_closed,
+    difficulty::NightmareTerrain,
+    levels::{CurrentLevel, LevelScreens, PoemState},
+    player::PlayerMarker,
+    snapshot::{LevelSnapshot, SNAPSHOT_VERSION, check_ron_version},
+    terrain::{
+        FinishMarker, Killzones, LastGeneration, RequiredFinishes, TerrainHistory, TerrainSeed,
+        TimeDiluationMap, UpdateTimer, VoxelizedView, finish_material, replace_collider,
+        spawn_finish, update_terrain, update_time,
+    },
+};
+
+/// Practice mode (`--practice`): F5 takes a savestate of the level and writes it to
+/// `--savestate`, F9 restores it.
+pub struct PracticePlugin;
+
+impl Plugin for PracticePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Savestates>();
+        app.add_systems(
+            Update,
+            (take_savestate, restore_savestate)
+                .chain()
+                .before(update_time)
+                .before(update_terrain)
+                .run_if(
+                    in_state(LevelScreens::Level)
+                        .and(console_closed)
+                        .and(|opts: Res<Opts>| opts.practice),
+                ),
+        );
+    }
+}
+
+/// A level in progress and the player in it.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Savestate {
+    pub level: LevelSnapshot,
+    pub player: Transform,
+    pub velocity: Vec2,
+    pub angular_velocity: f32,
+    pub poem_elapsed: Duration,
+    /// The lanterns, [`crate::anchor`] rebuilds the time sources of the level from them.
+    pub anchors: Vec<AnchorState>,
+    /// The shrinking bubble and the spreading lava, on Nightmare only.
+    pub nightmare: Option<NightmareTerrain>,
+}
+
+#[derive(Serialize)]
+struct VersionedRef<'a> {
+    version: u32,
+    savestate: &'a Savestate,
+}
+
+/// The version was checked with [`check_ron_version`] already.
+#[derive(Deserialize)]
+struct Versioned {
+    savestate: Savestate,
+}
+
+/// Stored as RON starting with [`SNAPSHOT_VERSION`], like [`LevelSnapshot`].
+impl Savestate {
+    pub fn to_ron(&self) -> String {
+        let versioned = VersionedRef {
+            version: SNAPSHOT_VERSION,
+            savestate: self,
+        };
+        ron::ser::to_string_pretty(&versioned, ron::ser::PrettyConfig::default())
+            .expect("savestates are always serializable")
+    }
+
+    pub fn from_ron(content: &str) -> Result<Savestate, String> {
+        check_ron_version(content)?;
+        let versioned: Versioned = ron::from_str(content).map_err(|e| e.to_string())?;
+        Ok(versioned.savestate)
+    }
+
+    pub fn load(path: &Path) -> Result<Savestate, String> {
+        let content =
+            std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        Savestate::from_ron(&content).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        std::fs::write(path, self.to_ron()).map_err(|e| format!("{}: {e}", path.display()))
+    }
+}
+
+/// The last savestate taken, kept across attempts and levels.
+#[derive(Resource, Default)]
+struct Savestates {
+    last: Option<Savestate>,
+}
+
+fn take_savestate(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut savestates: ResMut<Savestates>,
+    opts: Res<Opts>,
+    current_level: Res<CurrentLevel>,
+    seed: Res<TerrainSeed>,
+    terrain: Single<(
+        &VoxelizedView,
+        &TimeDiluationMap,
+        &UpdateTimer,
+        Option<&NightmareTerrain>,
+    )>,
+    killzones: Single<&Killzones>,
+    player: Single<(&Transform, &LinearVelocity, &AngularVelocity), With<PlayerMarker>>,
+    bones: Query<&Transform, With<FinishMarker>>,
+    anchors: Query<(&Transform, &TimeAnchor, Has<Carried>)>,
+    poem: Single<&PoemState>,
+) {
+    if !keys.just_pressed(KeyCode::F5) {
+        return;
+    }
+    let (voxels, time, timer, nightmare) = *terrain;
+    let (transform, velocity, angular_velocity) = *player;
+    let savestate = Savestate {
+        level: LevelSnapshot::capture(
+            current_level.0,
+            seed.0,
+            (voxels, time, timer),
+            *killzones,
+            bones.iter().map(|bone| bone.translation.xy()),
+        ),
+        player: *transform,
+        velocity: velocity.0,
+        angular_velocity: angular_velocity.0,
+        poem_elapsed: poem.timer.elapsed(),
+        anchors: anchors
+            .iter()
+            .map(|(transform, anchor, carried)| AnchorState::capture(transform, anchor, carried))
+            .collect(),
+        nightmare: nightmare.cloned(),
+    };
+    match savestate.save(&opts.savestate) {
+        Ok(()) => info!("savestate written to {}", opts.savestate.display()),
+        Err(e) => warn!("{e}, the savestate is only kept until the game is closed"),
+    }
+    savestates.last = Some(savestate);
+}
+
+fn restore_savestate(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut savestates: ResMut<Savestates>,
+    opts: Res<Opts>,
+    current_level: Res<CurrentLevel>,
+    mut terrain: Single<(
+        Entity,
+        &mut VoxelizedView,
+        &mut TimeDiluationMap,
+        &mut UpdateTimer,
+        &mut TerrainHistory,
+    )>,
+    mut killzones: Single<(Entity, &mut Killzones)>,
+    mut player: Single<
+        (&mut Transform, &mut LinearVelocity, &mut AngularVelocity),
+        With<PlayerMarker>,
+    >,
+    bones: Query<Entity, With<FinishMarker>>,
+    anchors: Query<Entity, With<TimeAnchor>>,
+    mut poem: Single<&mut PoemState>,
+    mut required_finishes: ResMut<RequiredFinishes>,
+    mut last_generation: ResMut<LastGeneration>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut colors: ResMut<Assets<ColorMaterial>>,
+    asset_server: Res<AssetServer>,
+) {
+    if !keys.just_pressed(KeyCode::F9) {
+        return;
+    }
+    // a savestate from an earlier session
+    if savestates.last.is_none() && opts.savestate.exists() {
+        match Savestate::load(&opts.savestate) {
+            Ok(savestate) => savestates.last = Some(savestate),
+            Err(e) => warn!("{e}"),
+        }
+    }
+    let Some(savestate) = &savestates.last else {
+        info!("no savestate yet, press F5 to take one");
+        return;
+    };
+    let level = &savestate.level;
+    if level.level != current_level.0 {
+        info!(
+            "the savestate is of level {}, not this one",
+            level.level + 1
+        );
+        return;
+    }
+
+    let (entity, voxels, time, timer, history) = &mut *terrain;
+    **voxels = level.voxels.clone();
+    **time = level.time.clone();
+    **timer = level.update_timer.clone();
+    // rewinding would go back to before the savestate
+    history.0.clear();
+    last_generation.born.clear();
+    last_generation.killed.clear();
+    replace_collider(&mut commands, *entity, voxels.collider());
+    match &savestate.nightmare {
+        Some(nightmare) => commands.entity(*entity).insert(nightmare.clone()),
+        None => commands.entity(*entity).remove::<NightmareTerrain>(),
+    };
+
+    let (entity, killzones) = &mut *killzones;
+    **killzones = level.killzones.clone();
+    replace_collider(&mut commands, *entity, killzones.collider());
+
+    let (transform, velocity, angular_velocity) = &mut *player;
+    **transform = savestate.player;
+    velocity.0 = savestate.velocity;
+    angular_velocity.0 = savestate.angular_velocity;
+
+    for bone in &bones {
+        commands.entity(bone).despawn();
+    }
+    let mesh = meshes.add(Rectangle::new(20.0, 20.0));
+    let material = finish_material(&mut colors, &asset_server);
+    for &bone in &level.finishes {
+        spawn_finish(&mut commands, mesh.clone(), material.clone(), bone);
+    }
+    required_finishes.0 = level.finishes.len() as u32;
+
+    for anchor in &anchors {
+        commands.entity(anchor).despawn();
+    }
+    for anchor in &savestate.anchors {
+        anchor.spawn(&mut commands, &mut meshes, &mut colors);
+    }
+
+    poem.timer.set_elapsed(savestate.poem_elapsed);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::terrain::{Killzones, TimeDiluationMap, UpdateTimer, VoxelizedView};
+
+    use super::*;
+
+    fn savestate() -> Savestate {
+        Savestate {
+            level: LevelSnapshot {
+                level: 1,
+                seed: 4,
+                voxels: VoxelizedView::empty(),
+                killzones: Killzones::empty(),
+                time: TimeDiluationMap::for_level(1),
+                update_timer: UpdateTimer(Timer::from_seconds(2.0, TimerMode::Repeating)),
+                finishes: vec![Vec2::new(100.0, -60.0)],
+            },
+            player: Transform::from_xyz(20.0, 40.0, 1.0),
+            velocity: Vec2::new(150.0, -20.0),
+            angular_velocity: 0.5,
+            poem_elapsed: Duration::from_secs(12),
+            anchors: vec![AnchorState {
+                position: Vec2::new(-30.0, 10.0),
+                remaining: Timer::from_seconds(20.0, TimerMode::Once),
+                carried: true,
+            }],
+            nightmare: None,
+        }
+    }
+
+    #[test]
+    fn round_trips_with_its_version() {
+        let savestate = savestate();
+        let ron = savestate.to_ron();
+        assert!(ron.starts_with(&format!("(\n    version: {SNAPSHOT_VERSION},")));
+        assert_eq!(Savestate::from_ron(&ron), Ok(savestate));
+        let other = ron.replacen("version: 1,", "version: 2,", 1);
+        assert!(
+            Savestate::from_ron(&other)
+                .unwrap_err()
+                .contains("version 2")
+        );
+    }
+}
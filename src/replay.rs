@@ -0,0 +1,621 @@
+use std::{path::Path, time::Duration};
+
+use bevy::{input::InputSystems, prelude::*, time::TimeUpdateStrategy};
+
+use crate::{
+    Opts,
+    assist::Assists,
+    difficulty::Difficulty,
+    levels::{CurrentLevel, LevelScreens},
+    player::PlayerMarker,
+    terrain::TerrainSeed,
+};
+
+const MAGIC: &[u8; 4] = b"DBRP";
+const VERSION: u8 = 2;
+
+/// Records the inputs of a level attempt (`--record`) and plays them back (`--replay`).
+///
+/// The recording keeps how long each frame took, the replay advances its frames by exactly
+/// that, so the same inputs meet the same physics steps and terrain generations while the
+/// recorded attempt itself was played at the real frame rate.
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(LevelScreens::Level),
+            (
+                align_fixed_time,
+                (start_recording, record_actions)
+                    .chain()
+                    .run_if(resource_exists::<Recorder>),
+                (start_replay, feed_replay)
+                    .chain()
+                    .run_if(resource_exists::<Replay>),
+            )
+                .run_if(resource_exists::<Recorder>.or(resource_exists::<Replay>)),
+        );
+        app.add_systems(
+            OnExit(LevelScreens::Level),
+            (
+                save_recording.run_if(resource_exists::<Recorder>),
+                stop_replay.run_if(resource_exists::<Replay>),
+            ),
+        );
+        // Inputs are read after the keyboard, a level is entered after `PreUpdate`, so its
+        // first frame is handled by the `OnEnter` systems above.
+        app.add_systems(
+            PreUpdate,
+            (
+                record_actions.run_if(resource_exists::<Recorder>),
+                feed_replay.run_if(resource_exists::<Replay>),
+            )
+                .after(InputSystems)
+                .run_if(in_state(LevelScreens::Level)),
+        );
+        app.add_systems(
+            Startup,
+            apply_replay_assists.run_if(resource_exists::<Replay>),
+        );
+    }
+}
+
+/// The keys held during one frame.
+///
+/// The low byte holds the actions that are pressed, the high byte the actions that were
+/// pressed this frame, a tap shorter than a frame sets only the latter.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct Actions(u16);
+
+impl Actions {
+    pub const LEFT: u16 = 1;
+    pub const RIGHT: u16 = 1 << 1;
+    pub const UP: u16 = 1 << 2;
+    pub const DOWN: u16 = 1 << 3;
+    pub const JUMP: u16 = 1 << 4;
+    pub const LANTERN: u16 = 1 << 5;
+    pub const REWIND: u16 = 1 << 6;
+
+    /// The keys of each action, the first one is pressed when replaying.
+    const KEYS: [(u16, &[KeyCode]); 7] = [
+        (Actions::LEFT, &[KeyCode::KeyA, KeyCode::ArrowLeft]),
+        (Actions::RIGHT, &[KeyCode::KeyD, KeyCode::ArrowRight]),
+        (Actions::UP, &[KeyCode::KeyW, KeyCode::ArrowUp]),
+        (Actions::DOWN, &[KeyCode::KeyS, KeyCode::ArrowDown]),
+        (Actions::JUMP, &[KeyCode::Space]),
+        (Actions::LANTERN, &[KeyCode::KeyE]),
+        (Actions::REWIND, &[KeyCode::KeyR]),
+    ];
+
+    pub fn read(keys: &ButtonInput<KeyCode>) -> Actions {
+        let mut actions = 0;
+        for (action, codes) in Actions::KEYS {
+            if keys.any_pressed(codes.iter().copied()) {
+                actions |= action;
+            }
+            if keys.any_just_pressed(codes.iter().copied()) {
+                actions |= action << 8;
+            }
+        }
+        Actions(actions)
+    }
+
+    pub fn pressed(self, action: u16) -> bool {
+        self.0 & action != 0
+    }
+
+    pub fn just_pressed(self, action: u16) -> bool {
+        self.0 & (action << 8) != 0
+    }
+
+    /// Replaces the keyboard state of every action with the recorded one.
+    fn apply(self, keys: &mut ButtonInput<KeyCode>) {
+        for (action, codes) in Actions::KEYS {
+            for &code in codes {
+                keys.reset(code);
+            }
+            let key = codes[0];
+            if self.pressed(action) || self.just_pressed(action) {
+                keys.press(key);
+            }
+            if !self.pressed(action) {
+                keys.release(key);
+            }
+            if !self.just_pressed(action) {
+                keys.clear_just_pressed(key);
+            }
+        }
+    }
+}
+
+/// One frame of a recording.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct Tick {
+    pub actions: Actions,
+    /// The real time the frame advanced by, frames longer than [`u32::MAX`] nanoseconds are
+    /// cut short, the game never advances that far in one frame anyway.
+    pub delta: Duration,
+}
+
+impl Tick {
+    fn delta_nanos(self) -> u32 {
+        self.delta.as_nanos().min(u32::MAX as u128) as u32
+    }
+}
+
+/// The inputs of one level attempt and what is needed to start it the same way again.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recording {
+    /// Index into [`crate::RequiredAssets::levels`].
+    pub level: u32,
+    pub seed: u64,
+    pub difficulty: Difficulty,
+    pub assists: Assists,
+    /// One entry per frame, from the first frame of the level to the one leaving it.
+    pub ticks: Vec<Tick>,
+}
+
+impl Recording {
+    /// A header followed by the ticks as runs of `(u16 length, u16 actions, u32 nanoseconds)`,
+    /// little endian.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&self.level.to_le_bytes());
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.push(match self.difficulty {
+            Difficulty::Dreamer => 0,
+            Difficulty::Wanderer => 1,
+            Difficulty::Nightmare => 2,
+        });
+        bytes.extend_from_slice(&self.assists.game_speed.to_le_bytes());
+        bytes.push(self.assists.invincible as u8 | (self.assists.infinite_timer as u8) << 1);
+        bytes.extend_from_slice(&(self.ticks.len() as u32).to_le_bytes());
+        for run in self
+            .ticks
+            .chunk_by(|a, b| a.actions == b.actions && a.delta_nanos() == b.delta_nanos())
+        {
+            for part in run.chunks(u16::MAX as usize) {
+                bytes.extend_from_slice(&(part.len() as u16).to_le_bytes());
+                bytes.extend_from_slice(&part[0].actions.0.to_le_bytes());
+                bytes.extend_from_slice(&part[0].delta_nanos().to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Recording, String> {
+        let mut reader = Reader(bytes);
+        if reader.take(4)? != MAGIC {
+            return Err("not a replay".to_string());
+        }
+        let version = reader.take(1)?[0];
+        if version != VERSION {
+            return Err(format!("replay version {version} is not supported"));
+        }
+        let level = u32::from_le_bytes(reader.array()?);
+        let seed = u64::from_le_bytes(reader.array()?);
+        let difficulty = match reader.take(1)?[0] {
+            0 => Difficulty::Dreamer,
+            1 => Difficulty::Wanderer,
+            2 => Difficulty::Nightmare,
+            other => return Err(format!("unknown difficulty {other}")),
+        };
+        let game_speed = f32::from_le_bytes(reader.array()?);
+        let flags = reader.take(1)?[0];
+        let len = u32::from_le_bytes(reader.array()?) as usize;
+        let mut ticks = Vec::new();
+        while ticks.len() < len {
+            let count = u16::from_le_bytes(reader.array()?) as usize;
+            let tick = Tick {
+                actions: Actions(u16::from_le_bytes(reader.array()?)),
+                delta: Duration::from_nanos(u32::from_le_bytes(reader.array()?) as u64),
+            };
+            ticks.extend(std::iter::repeat_n(tick, count));
+        }
+        if ticks.len() != len || !reader.0.is_empty() {
+            return Err("the ticks do not match their count".to_string());
+        }
+        Ok(Recording {
+            level,
+            seed,
+            difficulty,
+            assists: Assists {
+                game_speed,
+                invincible: flags & 1 != 0,
+                infinite_timer: flags & 2 != 0,
+            },
+            ticks,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Recording, String> {
+        let bytes = std::fs::read(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        Recording::decode(&bytes).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        std::fs::write(path, self.encode()).map_err(|e| format!("{}: {e}", path.display()))
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn take(&mut self, n: usize) -> Result<&[u8], String> {
+        if self.0.len() < n {
+            return Err("the replay ends early".to_string());
+        }
+        let (taken, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+}
+
+/// Present with `--record`, the attempt is written when the level is left.
+#[derive(Resource)]
+pub struct Recorder {
+    pub path: std::path::PathBuf,
+    pub recording: Option<Recording>,
+}
+
+/// Present with `--replay`, the keyboard follows the recording until it runs out.
+#[derive(Resource)]
+pub struct Replay {
+    pub recording: Recording,
+    /// The next tick to feed, `None` once the recorded attempt is over.
+    pub tick: Option<usize>,
+}
+
+impl Replay {
+    pub fn new(recording: Recording) -> Replay {
+        Replay {
+            recording,
+            tick: Some(0),
+        }
+    }
+}
+
+/// Options that make a run start the way the recording started.
+pub fn replay_opts(opts: &mut Opts, recording: &Recording) {
+    opts.level = Some(recording.level + 1);
+    opts.seed = recording.seed;
+    opts.difficulty = recording.difficulty;
+    opts.skip_intro = true;
+}
+
+/// Fixed steps carry time over between frames, drop it so the physics steps of an attempt do
+/// not depend on what happened before it.
+fn align_fixed_time(mut time: ResMut<Time<Fixed>>) {
+    let overstep = time.overstep();
+    time.discard_overstep(overstep);
+}
+
+fn start_recording(
+    mut recorder: ResMut<Recorder>,
+    current_level: Res<CurrentLevel>,
+    seed: Res<TerrainSeed>,
+    difficulty: Res<Difficulty>,
+    assists: Res<Assists>,
+) {
+    recorder.recording = Some(Recording {
+        level: current_level.0,
+        seed: seed.0,
+        difficulty: *difficulty,
+        assists: *assists,
+        ticks: Vec::new(),
+    });
+}
+
+fn record_actions(
+    mut recorder: ResMut<Recorder>,
+    keys: Res<ButtonInput<KeyCode>>,
+    time: Res<Time<Real>>,
+) {
+    if let Some(recording) = &mut recorder.recording {
+        recording.ticks.push(Tick {
+            actions: Actions::read(&keys),
+            delta: time.delta(),
+        });
+    }
+}
+
+/// Writes the attempt, a later attempt replaces it.
+fn save_recording(mut recorder: ResMut<Recorder>) {
+    let Some(recording) = recorder.recording.take() else {
+        return;
+    };
+    match recording.save(&recorder.path) {
+        Ok(()) => info!(
+            "recorded {} frames of level {} to {}",
+            recording.ticks.len(),
+            recording.level + 1,
+            recorder.path.display()
+        ),
+        Err(e) => warn!("{e}"),
+    }
+}
+
+/// Only the first attempt is replayed, later ones are played live.
+fn start_replay(mut replay: ResMut<Replay>, current_level: Res<CurrentLevel>) {
+    if replay.recording.level != current_level.0 || replay.tick != Some(0) {
+        replay.tick = None;
+    }
+}
+
+/// Presses the keys of the current tick and lets the next frame take as long as the recorded one.
+fn feed_replay(
+    mut replay: ResMut<Replay>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut strategy: ResMut<TimeUpdateStrategy>,
+    player: Query<&Transform, With<PlayerMarker>>,
+) {
+    let Some(tick) = replay.tick else {
+        return;
+    };
+    let ticks = &replay.recording.ticks;
+    match ticks.get(tick) {
+        Some(current) => {
+            current.actions.apply(&mut keys);
+            if let Some(next) = ticks.get(tick + 1) {
+                *strategy = TimeUpdateStrategy::ManualDuration(next.delta);
+            }
+            replay.tick = Some(tick + 1);
+        }
+        None => finish_replay(&mut replay, &mut keys, &mut strategy, &player),
+    }
+}
+
+/// The recorded attempt left the level, a replay that did not keep up with it ends as well.
+fn stop_replay(
+    mut replay: ResMut<Replay>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut strategy: ResMut<TimeUpdateStrategy>,
+    player: Query<&Transform, With<PlayerMarker>>,
+) {
+    if replay.tick.is_some() {
+        finish_replay(&mut replay, &mut keys, &mut strategy, &player);
+    }
+}
+
+/// Logs where the player ended up and hands the keyboard and the clock back.
+fn finish_replay(
+    replay: &mut Replay,
+    keys: &mut ButtonInput<KeyCode>,
+    strategy: &mut TimeUpdateStrategy,
+    player: &Query<&Transform, With<PlayerMarker>>,
+) {
+    if let Ok(player) = player.single() {
+        info!(
+            "replay finished after {} of {} frames, the player is at {}",
+            replay.tick.unwrap_or_default(),
+            replay.recording.ticks.len(),
+            player.translation.xy()
+        );
+    }
+    keys.reset_all();
+    *strategy = TimeUpdateStrategy::Automatic;
+    replay.tick = None;
+}
+
+fn apply_replay_assists(replay: Res<Replay>, mut assists: ResMut<Assists>) {
+    *assists = replay.recording.assists;
+}
+
+/// Gives every frame the length of the first recorded one until the replayed level starts,
+/// the frame entering the level is timed before the replay knows it started.
+pub fn replay_frame_time(app: &mut App, recording: &Recording) {
+    let first = recording
+        .ticks
+        .first()
+        .map_or(Duration::ZERO, |tick| tick.delta);
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(first));
+}
+
+#[cfg(test)]
+mod tests {
+    use avian2d::prelude::*;
+    use bevy::{
+        mesh::MeshPlugin, scene::ScenePlugin, state::app::StatesPlugin, transform::TransformPlugin,
+    };
+    use clap::Parser;
+
+    use crate::{
+        gameplay::GRAVITY,
+        layout::{LevelLayout, load_level_image},
+        player::player_body,
+        player_controller::update_player_position,
+        terrain::{
+            LastGeneration, LevelMemory, TerrainFrozen, TerrainHistory, TerrainTimings,
+            TimeDiluationMap, UPDATE_INTERVAL, UpdateTimer, update_terrain, update_time,
+            voxel_to_world,
+        },
+        test_support::TempDir,
+    };
+
+    use super::*;
+
+    const FRAMES: usize = 400;
+
+    /// The first level with its terrain and physics, but nothing that needs a window.
+    fn headless_level() -> App {
+        let image =
+            load_level_image(Path::new("assets/levels/level_1.png")).expect("level 1 loads");
+        let layout = LevelLayout::parse(&image).expect("level 1 parses");
+        let (x, y) = layout.spawn().expect("level 1 has a spawn");
+        let spawn = voxel_to_world(x, y) + Vec2::splat(10.0);
+
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            MeshPlugin,
+            ScenePlugin,
+            TransformPlugin,
+            StatesPlugin,
+            PhysicsPlugins::default().with_length_unit(20.0),
+            ReplayPlugin,
+        ))
+        .insert_resource(Gravity(Vec2::NEG_Y * GRAVITY))
+        .insert_resource(Opts::parse_from(["dornburg"]))
+        .insert_resource(Difficulty::Wanderer)
+        .init_resource::<Assists>()
+        .insert_resource(CurrentLevel(0))
+        .insert_resource(TerrainSeed(0))
+        .init_resource::<TerrainTimings>()
+        .init_resource::<TerrainFrozen>()
+        .init_resource::<LastGeneration>()
+        .init_resource::<ButtonInput<KeyCode>>()
+        .insert_state(LevelScreens::None)
+        .add_systems(
+            Update,
+            (update_time, update_terrain, update_player_position)
+                .chain()
+                .run_if(in_state(LevelScreens::Level)),
+        );
+
+        let mut terrain = app.world_mut().spawn((
+            RigidBody::Static,
+            Transform::default(),
+            layout.voxels.clone(),
+            TimeDiluationMap::for_level(0),
+            UpdateTimer(Timer::from_seconds(UPDATE_INTERVAL, TimerMode::Repeating)),
+            TerrainHistory::default(),
+            LevelMemory::for_level(&layout.voxels, 0),
+        ));
+        if let Some(collider) = layout.voxels.collider() {
+            terrain.insert(collider);
+        }
+        app.world_mut().spawn((
+            Transform::from_translation(spawn.extend(1.0)),
+            player_body(),
+        ));
+        // `App::run` would do this, physics registers resources when it finishes
+        app.finish();
+        app.cleanup();
+        // the first frame applies state changes before `PreUpdate`, later ones after it
+        app.update();
+        app
+    }
+
+    fn enter(app: &mut App, screen: LevelScreens) {
+        app.world_mut()
+            .resource_mut::<NextState<LevelScreens>>()
+            .set(screen);
+        app.update();
+    }
+
+    fn player_position(app: &mut App) -> Vec2 {
+        app.world_mut()
+            .query_filtered::<&Transform, With<PlayerMarker>>()
+            .single(app.world())
+            .unwrap()
+            .translation
+            .xy()
+    }
+
+    /// Runs right and jumps at irregular intervals, with taps shorter than a frame.
+    fn scripted_input(frame: usize, keys: &mut ButtonInput<KeyCode>) {
+        keys.clear();
+        if frame % 90 < 60 {
+            keys.press(KeyCode::KeyD);
+        } else {
+            keys.release(KeyCode::KeyD);
+            keys.press(KeyCode::ArrowLeft);
+        }
+        if frame % 90 == 75 {
+            keys.release(KeyCode::ArrowLeft);
+        }
+        if frame % 37 == 0 {
+            keys.press(KeyCode::Space);
+            if frame % 2 == 0 {
+                keys.release(KeyCode::Space);
+            }
+        } else {
+            keys.release(KeyCode::Space);
+        }
+    }
+
+    #[test]
+    fn round_trips_through_bytes() {
+        let recording = Recording {
+            level: 2,
+            seed: 0xdead_beef,
+            difficulty: Difficulty::Nightmare,
+            assists: Assists {
+                game_speed: 0.5,
+                invincible: true,
+                infinite_timer: false,
+            },
+            ticks: [Actions(0); 70_000]
+                .into_iter()
+                .chain([Actions(Actions::JUMP | Actions::JUMP << 8), Actions(3)])
+                .map(|actions| Tick {
+                    actions,
+                    delta: Duration::from_nanos(16_666_667),
+                })
+                .chain([Tick {
+                    actions: Actions(3),
+                    delta: Duration::from_millis(250),
+                }])
+                .collect(),
+        };
+        let bytes = recording.encode();
+        assert!(bytes.len() < 80);
+        assert_eq!(Recording::decode(&bytes), Ok(recording));
+        assert!(Recording::decode(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Recording::decode(b"DBRP\x03").is_err());
+    }
+
+    #[test]
+    fn replays_end_where_the_recording_ended() {
+        let dir = TempDir::new("replay");
+        let path = dir.join("level.bin");
+
+        let mut recorded = headless_level();
+        recorded.insert_resource(Recorder {
+            path: path.clone(),
+            recording: None,
+        });
+        // uneven frames, as the game gets them
+        let frame_time = |frame: usize| {
+            TimeUpdateStrategy::ManualDuration(Duration::from_millis(9 + frame as u64 * 7 % 13))
+        };
+        recorded.insert_resource(frame_time(FRAMES));
+        enter(&mut recorded, LevelScreens::Level);
+        for frame in 0..FRAMES {
+            scripted_input(frame, &mut recorded.world_mut().resource_mut());
+            recorded.insert_resource(frame_time(frame));
+            recorded.update();
+        }
+        let end = player_position(&mut recorded);
+        enter(&mut recorded, LevelScreens::Restart);
+
+        let recording = Recording::load(&path).unwrap();
+        // the frame entering the level, the scripted ones and the one leaving it
+        assert_eq!(recording.ticks.len(), FRAMES + 2);
+
+        let mut replayed = headless_level();
+        replay_frame_time(&mut replayed, &recording);
+        replayed.insert_resource(Replay::new(recording));
+        enter(&mut replayed, LevelScreens::Level);
+        for _ in 0..FRAMES {
+            replayed.update();
+        }
+        let start = player_position(&mut headless_level());
+        assert!(end.distance(start) > 100.0, "the player barely moved");
+        assert_eq!(player_position(&mut replayed), end);
+        // the game runs on its own clock again
+        enter(&mut replayed, LevelScreens::Restart);
+        assert!(matches!(
+            *replayed.world().resource::<TimeUpdateStrategy>(),
+            TimeUpdateStrategy::Automatic
+        ));
+    }
+}
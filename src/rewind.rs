@@ -0,0 +1,206 @@
+use bevy::prelude::*;
+
+use crate::{
+    RequiredAssets,
+    dev::console_closed,
+    levels::LevelScreens,
+    player::PlayerMarker,
+    terrain::{
+        TerrainHistory, TerrainMaterial, TimeDiluationMap, UpdateTimer, VoxelizedView,
+        replace_collider, update_terrain, update_terrain_material, voxel_to_world,
+    },
+};
+
+/// Rewinds per level, a rewind lasts as long as R is held or the history runs out.
+pub const REWIND_CHARGES: u32 = 3;
+/// Seconds between two generations played back while rewinding.
+const REWIND_STEP: f32 = 0.3;
+
+pub struct RewindPlugin;
+
+impl Plugin for RewindPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(RewindCharges(REWIND_CHARGES));
+        app.insert_resource(Rewinding {
+            active: false,
+            step: Timer::from_seconds(REWIND_STEP, TimerMode::Repeating),
+        });
+        app.add_systems(
+            OnEnter(LevelScreens::Level),
+            (reset_rewind, spawn_rewind_text),
+        );
+        app.add_systems(
+            Update,
+            (
+                (start_rewind.run_if(console_closed), rewind)
+                    .chain()
+                    .before(update_terrain),
+                rewind_effect.after(update_terrain_material),
+                update_rewind_text,
+            )
+                .run_if(in_state(LevelScreens::Level)),
+        );
+    }
+}
+
+#[derive(Resource)]
+pub struct RewindCharges(pub u32);
+
+#[derive(Resource)]
+pub struct Rewinding {
+    pub active: bool,
+    step: Timer,
+}
+
+fn reset_rewind(mut charges: ResMut<RewindCharges>, mut rewinding: ResMut<Rewinding>) {
+    charges.0 = REWIND_CHARGES;
+    rewinding.active = false;
+}
+
+fn start_rewind(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut charges: ResMut<RewindCharges>,
+    mut rewinding: ResMut<Rewinding>,
+    history: Query<&TerrainHistory>,
+) {
+    let has_history = history.iter().any(|h| !h.0.is_empty());
+    if keys.just_pressed(KeyCode::KeyR) && charges.0 > 0 && has_history {
+        charges.0 -= 1;
+        rewinding.active = true;
+        // the first generation is restored right away
+        let duration = rewinding.step.duration();
+        rewinding.step.set_elapsed(duration);
+    }
+}
+
+/// `previous` with the voxels [`crate::terrain::evolve`] leaves alone kept as they are now, so
+/// a rewind never buries the player. `player` is relative to the terrain.
+fn rewound(
+    current: &VoxelizedView,
+    mut previous: VoxelizedView,
+    time: &TimeDiluationMap,
+    player: Vec2,
+) -> VoxelizedView {
+    for x in 0..128 {
+        for y in 0..128 {
+            if time.holds(player, voxel_to_world(x, y)) {
+                previous.set(x, y, current.get(x, y));
+            }
+        }
+    }
+    previous
+}
+
+/// Restores one generation from the history every [`REWIND_STEP`] while R is held.
+/// The terrain does not evolve while rewinding.
+fn rewind(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    mut rewinding: ResMut<Rewinding>,
+    player: Single<&Transform, With<PlayerMarker>>,
+    mut terrain: Query<(
+        Entity,
+        &Transform,
+        &mut VoxelizedView,
+        &mut TimeDiluationMap,
+        &mut TerrainHistory,
+        &mut UpdateTimer,
+    )>,
+) {
+    if !rewinding.active {
+        return;
+    }
+    if !keys.pressed(KeyCode::KeyR) {
+        rewinding.active = false;
+        return;
+    }
+    rewinding.step.tick(time.delta());
+    let restore = rewinding.step.just_finished();
+    for (entity, transform, mut voxels, mut time_map, mut history, mut timer) in &mut terrain {
+        timer.0.reset();
+        if !restore {
+            continue;
+        }
+        match history.0.pop_back() {
+            Some((previous_voxels, previous_time)) => {
+                let player = player.translation.xy() - transform.translation.xy();
+                *voxels = rewound(&voxels, previous_voxels, &time_map, player);
+                *time_map = previous_time;
+                replace_collider(&mut commands, entity, voxels.collider());
+            }
+            None => rewinding.active = false,
+        }
+    }
+}
+
+fn rewind_effect(
+    rewinding: Res<Rewinding>,
+    terrain: Query<&MeshMaterial2d<TerrainMaterial>>,
+    mut materials: ResMut<Assets<TerrainMaterial>>,
+) {
+    for material in &terrain {
+        if let Some(material) = materials.get_mut(&material.0) {
+            material.level.z = rewinding.active as i32;
+        }
+    }
+}
+
+#[derive(Component)]
+struct RewindTextMarker;
+
+fn spawn_rewind_text(mut commands: Commands, assets: Res<RequiredAssets>) {
+    commands.spawn((
+        DespawnOnExit(LevelScreens::Level),
+        Node {
+            position_type: PositionType::Absolute,
+            top: px(20),
+            right: px(100),
+            ..Default::default()
+        },
+        children![(
+            Text::new(""),
+            TextFont {
+                font: assets.font.clone().unwrap(),
+                ..Default::default()
+            },
+            RewindTextMarker
+        )],
+    ));
+}
+
+fn update_rewind_text(
+    mut text: Single<&mut Text, With<RewindTextMarker>>,
+    charges: Res<RewindCharges>,
+) {
+    text.0 = format!("Rewinds: {}", charges.0);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::terrain::cell_center;
+
+    use super::*;
+
+    #[test]
+    fn rewinds_never_bury_the_player() {
+        let mut filled = VoxelizedView::empty();
+        for x in 0..128 {
+            for y in 0..128 {
+                filled.set(x, y, true);
+            }
+        }
+        let time = TimeDiluationMap::for_level(0);
+        for cell in [UVec2::new(0, 0), UVec2::new(64, 64), UVec2::new(127, 20)] {
+            let player = cell_center(cell);
+            let voxels = rewound(&VoxelizedView::empty(), filled.clone(), &time, player);
+            assert!(
+                !voxels.get(cell.x, cell.y),
+                "the player in {cell} was buried"
+            );
+            // the far side of the level is rewound
+            let far = if cell.x < 64 { 127 } else { 0 };
+            assert!(voxels.get(far, cell.y));
+        }
+    }
+}
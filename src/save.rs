@@ -0,0 +1,134 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{difficulty::Difficulty, ghost::Ghost, speedrun::Split};
+
+/// What the game remembers between runs, stored as RON.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(default)]
+pub struct SaveData {
+    /// The fastest finished run per difficulty.
+    pub best_runs: BTreeMap<Difficulty, BestRun>,
+    /// Like `best_runs`, for [`crate::speedrun::Standing::Assisted`] runs.
+    pub assisted_best_runs: BTreeMap<Difficulty, BestRun>,
+    /// The fastest attempt at each level of a clean run, by difficulty and level index.
+    pub ghosts: BTreeMap<Difficulty, BTreeMap<u32, Ghost>>,
+    pub settings: Settings,
+}
+
+/// Changed on the settings screen.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Settings {
+    /// Show the ghost of the fastest attempt at a level.
+    pub show_ghost: bool,
+    /// Log level attempts to `--telemetry`, see [`crate::telemetry::TelemetryPlugin`].
+    pub telemetry: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            show_ghost: true,
+            telemetry: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BestRun {
+    /// Seconds in levels, without intermissions.
+    pub time: f32,
+    pub splits: Vec<Split>,
+}
+
+impl SaveData {
+    /// Records a finished run, returns true if it is a new best time.
+    pub fn record_run(
+        &mut self,
+        difficulty: Difficulty,
+        assisted: bool,
+        time: f32,
+        splits: &[Split],
+    ) -> bool {
+        let runs = if assisted {
+            &mut self.assisted_best_runs
+        } else {
+            &mut self.best_runs
+        };
+        let improved = runs.get(&difficulty).is_none_or(|best| time < best.time);
+        if improved {
+            runs.insert(
+                difficulty,
+                BestRun {
+                    time,
+                    splits: splits.to_vec(),
+                },
+            );
+        }
+        improved
+    }
+
+    /// Keeps `ghost` if it finished the level faster, returns true if it did.
+    pub fn record_ghost(&mut self, difficulty: Difficulty, level: u32, ghost: Ghost) -> bool {
+        let ghosts = self.ghosts.entry(difficulty).or_default();
+        let improved = ghosts
+            .get(&level)
+            .is_none_or(|best| ghost.duration() < best.duration());
+        if improved {
+            ghosts.insert(level, ghost);
+        }
+        improved
+    }
+
+    pub fn ghost(&self, difficulty: Difficulty, level: u32) -> Option<&Ghost> {
+        self.ghosts.get(&difficulty)?.get(&level)
+    }
+
+    pub fn best_run(&self, difficulty: Difficulty, assisted: bool) -> Option<&BestRun> {
+        let runs = if assisted {
+            &self.assisted_best_runs
+        } else {
+            &self.best_runs
+        };
+        runs.get(&difficulty)
+    }
+}
+
+/// The save data and where it is stored, set with `--save`.
+#[derive(Resource)]
+pub struct SaveFile {
+    pub path: PathBuf,
+    pub data: SaveData,
+}
+
+impl SaveFile {
+    /// Starts with empty save data if the file does not exist or can not be read.
+    pub fn load(path: &Path) -> SaveFile {
+        let data = match std::fs::read_to_string(path) {
+            Ok(content) => ron::from_str(&content).unwrap_or_else(|e| {
+                warn!("{}: {e}, starting without save data", path.display());
+                SaveData::default()
+            }),
+            Err(_) => SaveData::default(),
+        };
+        SaveFile {
+            path: path.to_path_buf(),
+            data,
+        }
+    }
+
+    /// Writes the save data, warns if that fails.
+    pub fn store(&self) {
+        let content = ron::ser::to_string_pretty(&self.data, ron::ser::PrettyConfig::default())
+            .expect("the save data is always serializable");
+        if let Err(e) = std::fs::write(&self.path, content) {
+            warn!("{}: {e}, the save data was not stored", self.path.display());
+        }
+    }
+}
@@ -1,16 +1,31 @@
-use bevy::{
-    app::{App, Plugin},
-    state::{app::AppExtStates, state::States},
-};
+use bevy::{prelude::*, ui_widgets::Activate};
 
-use crate::main_screen::MainScreenPlugin;
+use crate::{
+    assist::AssistPlugin,
+    main_screen::MainScreenPlugin,
+    settings::SettingsPlugin,
+    transition::{Transition, TransitionPlugin, TransitionStyle},
+};
 
 pub struct ScreenPlugin;
 
 impl Plugin for ScreenPlugin {
     fn build(&self, app: &mut App) {
         app.init_state::<Screen>();
-        app.add_plugins(MainScreenPlugin);
+        app.add_plugins((
+            MainScreenPlugin,
+            AssistPlugin,
+            SettingsPlugin,
+            TransitionPlugin,
+        ));
+        app.add_systems(
+            Update,
+            handle_escape.run_if(
+                in_state(Screen::Help)
+                    .or(in_state(Screen::Assist))
+                    .or(in_state(Screen::Settings)),
+            ),
+        );
     }
 }
 
@@ -19,5 +34,19 @@ pub enum Screen {
     #[default]
     Main,
     Help,
+    Assist,
+    Settings,
     Gameplay,
 }
+
+/// Observer for the buttons leading back to the main menu.
+pub fn go_to_main(_: On<Activate>, mut transition: ResMut<Transition>) {
+    transition.start(Screen::Main, TransitionStyle::Fade);
+}
+
+/// Escape leads from the menus reachable from the main menu back to it.
+fn handle_escape(keys: Res<ButtonInput<KeyCode>>, mut transition: ResMut<Transition>) {
+    if keys.just_pressed(KeyCode::Escape) {
+        transition.start(Screen::Main, TransitionStyle::Fade);
+    }
+}
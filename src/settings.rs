@@ -0,0 +1,88 @@
+use bevy::{
+    feathers::{
+        controls::{ButtonProps, button, checkbox},
+        theme::ThemeBackgroundColor,
+        tokens,
+    },
+    prelude::*,
+    ui::Checked,
+    ui_widgets::{ValueChange, observe},
+};
+
+use crate::{
+    assist::set_checked,
+    save::SaveFile,
+    screens::{Screen, go_to_main},
+};
+
+/// The settings menu, reachable from the main menu, settings are kept in the save file.
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(Screen::Settings), setup_settings);
+        app.add_systems(OnExit(Screen::Settings), store_settings);
+    }
+}
+
+fn setup_settings(mut commands: Commands, save: Res<SaveFile>) {
+    commands
+        .spawn((
+            DespawnOnExit(Screen::Settings),
+            Node {
+                display: Display::Flex,
+                flex_direction: FlexDirection::Column,
+                width: percent(100),
+                height: percent(100),
+                row_gap: px(10),
+                ..Default::default()
+            },
+            ThemeBackgroundColor(tokens::WINDOW_BG),
+        ))
+        .with_children(|parent| {
+            let mut show_ghost = parent.spawn(checkbox(
+                (),
+                Spawn(Text::new("Show the ghost of your fastest attempt")),
+            ));
+            if save.data.settings.show_ghost {
+                show_ghost.insert(Checked);
+            }
+            show_ghost.observe(set_show_ghost);
+            let mut telemetry = parent.spawn(checkbox(
+                (),
+                Spawn(Text::new(
+                    "Keep a local log of deaths and paths, for `dornburg heatmap`",
+                )),
+            ));
+            if save.data.settings.telemetry {
+                telemetry.insert(Checked);
+            }
+            telemetry.observe(set_telemetry);
+            parent.spawn((
+                button(ButtonProps::default(), (), Spawn(Text::new("Back"))),
+                observe(go_to_main),
+            ));
+        });
+}
+
+fn set_show_ghost(
+    change: On<ValueChange<bool>>,
+    mut commands: Commands,
+    mut save: ResMut<SaveFile>,
+) {
+    save.data.settings.show_ghost = change.value;
+    set_checked(&mut commands, change.source, change.value);
+}
+
+fn set_telemetry(
+    change: On<ValueChange<bool>>,
+    mut commands: Commands,
+    mut save: ResMut<SaveFile>,
+) {
+    save.data.settings.telemetry = change.value;
+    set_checked(&mut commands, change.source, change.value);
+}
+
+fn store_settings(save: Res<SaveFile>) {
+    save.store();
+}
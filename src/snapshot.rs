@@ -0,0 +1,266 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::terrain::{Killzones, TimeDiluationMap, UpdateTimer, VoxelizedView};
+
+/// Raised whenever [`LevelSnapshot`] changes in a way older files can not be read with.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Starts the binary format, followed by the version as little endian `u32` and the snapshot
+/// as CBOR.
+const MAGIC: &[u8; 4] = b"DBLS";
+
+/// A level in progress, everything the terrain needs to continue where it was.
+///
+/// Stored as RON (`.ron`) or in a binary format (any other extension), both start with
+/// [`SNAPSHOT_VERSION`]. The voxel grids are rows of `#` and `.`, so RON snapshots can be read
+/// and edited like the level images.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LevelSnapshot {
+    /// Index into [`crate::RequiredAssets::levels`].
+    pub level: u32,
+    pub seed: u64,
+    pub voxels: VoxelizedView,
+    pub killzones: Killzones,
+    pub time: TimeDiluationMap,
+    /// When the terrain evolves next.
+    pub update_timer: UpdateTimer,
+    /// Centers of the bones not collected yet.
+    pub finishes: Vec<Vec2>,
+}
+
+#[derive(Serialize)]
+struct VersionedRef<'a> {
+    version: u32,
+    level: &'a LevelSnapshot,
+}
+
+/// The version was checked with [`Version`] already.
+#[derive(Deserialize)]
+struct Versioned {
+    level: LevelSnapshot,
+}
+
+/// Read first, so snapshots of other versions are rejected before their content is parsed.
+#[derive(Deserialize)]
+struct Version {
+    version: u32,
+}
+
+/// Checks the `version` of a RON file in the envelope of [`LevelSnapshot::to_ron`], which
+/// [`crate::practice::Savestate`] shares.
+pub fn check_ron_version(content: &str) -> Result<(), String> {
+    let Version { version } = ron::from_str(content).map_err(|e| e.to_string())?;
+    check_version(version)
+}
+
+fn check_version(version: u32) -> Result<(), String> {
+    if version == SNAPSHOT_VERSION {
+        Ok(())
+    } else {
+        Err(format!(
+            "snapshot version {version} is not supported, expected {SNAPSHOT_VERSION}"
+        ))
+    }
+}
+
+impl LevelSnapshot {
+    /// The level as the given components have it now.
+    pub fn capture(
+        level: u32,
+        seed: u64,
+        (voxels, time, timer): (&VoxelizedView, &TimeDiluationMap, &UpdateTimer),
+        killzones: &Killzones,
+        finishes: impl IntoIterator<Item = Vec2>,
+    ) -> LevelSnapshot {
+        LevelSnapshot {
+            level,
+            seed,
+            voxels: voxels.clone(),
+            killzones: killzones.clone(),
+            time: time.clone(),
+            update_timer: timer.clone(),
+            finishes: finishes.into_iter().collect(),
+        }
+    }
+
+    pub fn to_ron(&self) -> String {
+        let versioned = VersionedRef {
+            version: SNAPSHOT_VERSION,
+            level: self,
+        };
+        ron::ser::to_string_pretty(&versioned, ron::ser::PrettyConfig::default())
+            .expect("snapshots are always serializable")
+    }
+
+    pub fn from_ron(content: &str) -> Result<LevelSnapshot, String> {
+        check_ron_version(content)?;
+        let versioned: Versioned = ron::from_str(content).map_err(|e| e.to_string())?;
+        Ok(versioned.level)
+    }
+
+    pub fn to_binary(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&SNAPSHOT_VERSION.to_le_bytes());
+        ciborium::into_writer(self, &mut bytes).expect("snapshots are always serializable");
+        bytes
+    }
+
+    pub fn from_binary(bytes: &[u8]) -> Result<LevelSnapshot, String> {
+        let rest = bytes
+            .strip_prefix(MAGIC)
+            .ok_or("not a binary level snapshot")?;
+        let (version, content) = rest
+            .split_first_chunk::<4>()
+            .ok_or("the snapshot ends early")?;
+        check_version(u32::from_le_bytes(*version))?;
+        ciborium::from_reader(content).map_err(|e| e.to_string())
+    }
+
+    /// Writes RON if `path` ends in `.ron`, the binary format otherwise.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let bytes = if is_ron(path) {
+            self.to_ron().into_bytes()
+        } else {
+            self.to_binary()
+        };
+        std::fs::write(path, bytes).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    /// Reads either format, binary snapshots are told apart by their first bytes.
+    pub fn load(path: &Path) -> Result<LevelSnapshot, String> {
+        let bytes = std::fs::read(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        let snapshot = if bytes.starts_with(MAGIC) {
+            LevelSnapshot::from_binary(&bytes)
+        } else {
+            std::str::from_utf8(&bytes)
+                .map_err(|e| e.to_string())
+                .and_then(LevelSnapshot::from_ron)
+        };
+        snapshot.map_err(|e| format!("{}: {e}", path.display()))
+    }
+}
+
+fn is_ron(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "ron")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        difficulty::Difficulty,
+        layout::{LevelLayout, load_level_image},
+        terrain::{LevelMemory, cell_center, evolve},
+        test_support::TempDir,
+    };
+
+    use super::*;
+
+    /// The level as [`crate::terrain::spawn_level`] starts it.
+    fn start(layout: &LevelLayout, level: u32, seed: u64, difficulty: Difficulty) -> LevelSnapshot {
+        let mut time = TimeDiluationMap::for_level(level);
+        time.bubble = difficulty.bubble();
+        LevelSnapshot {
+            level,
+            seed,
+            voxels: layout.voxels.clone(),
+            killzones: layout.killzones.clone(),
+            time,
+            update_timer: UpdateTimer(Timer::from_seconds(
+                difficulty.update_interval(),
+                TimerMode::Repeating,
+            )),
+            finishes: layout
+                .finishes
+                .iter()
+                .map(|&(x, y)| cell_center(UVec2::new(x, y)))
+                .collect(),
+        }
+    }
+
+    /// Level 2 a few generations in, so every part of the snapshot differs from the level image.
+    fn evolved_level() -> LevelSnapshot {
+        let image =
+            load_level_image(Path::new("assets/levels/level_2.png")).expect("level 2 loads");
+        let layout = LevelLayout::parse(&image).expect("level 2 parses");
+        let mut snapshot = start(&layout, 1, 7, Difficulty::Nightmare);
+        let memory = LevelMemory::for_level(&layout.voxels, 1);
+        let player = Vec2::new(-300.0, 100.0);
+        for _ in 0..3 {
+            snapshot.time.advance(player, 2.2);
+            snapshot.voxels = evolve(&snapshot.voxels, &snapshot.time, &memory, 1, 7, player);
+        }
+        snapshot.killzones.set(3, 4, true);
+        snapshot.update_timer.0.tick(Duration::from_millis(700));
+        snapshot.finishes.pop();
+        assert_ne!(snapshot.voxels, layout.voxels);
+        snapshot
+    }
+
+    #[test]
+    fn round_trips_through_ron() {
+        let snapshot = evolved_level();
+        let ron = snapshot.to_ron();
+        assert!(ron.starts_with("(\n    version: 1,"));
+        assert_eq!(LevelSnapshot::from_ron(&ron), Ok(snapshot));
+    }
+
+    #[test]
+    fn round_trips_through_binary() {
+        let snapshot = evolved_level();
+        let bytes = snapshot.to_binary();
+        assert!(bytes.len() < snapshot.to_ron().len());
+        assert_eq!(LevelSnapshot::from_binary(&bytes), Ok(snapshot));
+    }
+
+    #[test]
+    fn round_trips_through_files() {
+        let snapshot = evolved_level();
+        let dir = TempDir::new("snapshot");
+        for name in ["level.ron", "level.snapshot"] {
+            let path = dir.join(name);
+            snapshot.save(&path).unwrap();
+            assert_eq!(LevelSnapshot::load(&path), Ok(snapshot.clone()));
+        }
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let snapshot = evolved_level();
+        let ron = snapshot.to_ron().replacen("version: 1,", "version: 2,", 1);
+        assert!(
+            LevelSnapshot::from_ron(&ron)
+                .unwrap_err()
+                .contains("version 2")
+        );
+        let mut bytes = snapshot.to_binary();
+        bytes[4] = 2;
+        assert!(
+            LevelSnapshot::from_binary(&bytes)
+                .unwrap_err()
+                .contains("version 2")
+        );
+        assert!(LevelSnapshot::from_binary(b"DBLS").is_err());
+        assert!(LevelSnapshot::from_binary(b"PNG").is_err());
+    }
+
+    #[test]
+    fn voxels_are_stored_as_rows() {
+        let snapshot = evolved_level();
+        let ron = snapshot.to_ron();
+        let row = (0..128)
+            .map(|x| if snapshot.voxels.get(x, 10) { '#' } else { '.' })
+            .collect::<String>();
+        assert!(ron.contains(&format!("\"{row}\"")));
+        let broken = ron.replacen(&row, &row[1..], 1);
+        assert!(
+            LevelSnapshot::from_ron(&broken)
+                .unwrap_err()
+                .contains("128 voxels wide")
+        );
+    }
+}
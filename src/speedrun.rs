@@ -0,0 +1,243 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Opts, RequiredAssets,
+    assist::Assists,
+    death::DeathCounter,
+    dev::console_closed,
+    levels::{CurrentLevel, LevelScreens},
+    screens::Screen,
+};
+
+/// Measures runs without the intermissions, with a split per level.
+pub struct SpeedrunPlugin;
+
+impl Plugin for SpeedrunPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RunTimer>();
+        app.insert_resource(ShowRunTimer(false));
+        app.add_systems(
+            OnEnter(Screen::Gameplay),
+            (reset_run_timer, spawn_run_timer_text),
+        );
+        app.add_systems(OnEnter(LevelScreens::Intermission), split);
+        app.add_systems(
+            Update,
+            tick_run_timer.run_if(in_state(Screen::Gameplay).and(in_state(LevelScreens::Level))),
+        );
+        app.add_systems(
+            Update,
+            (
+                toggle_run_timer.run_if(console_closed),
+                update_run_timer_text,
+            )
+                .chain()
+                .run_if(in_state(Screen::Gameplay)),
+        );
+    }
+}
+
+/// The time at which a level was finished.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Split {
+    /// Index into [`RequiredAssets::levels`].
+    pub level: u32,
+    /// Seconds since the start of the run, without intermissions.
+    pub time: f32,
+    /// Deaths on the level before it was finished, see [`DeathCounter`].
+    pub deaths: u32,
+}
+
+/// Real time spent in levels during the current run, so assists slowing the game do not shorten it.
+#[derive(Resource, Default)]
+pub struct RunTimer {
+    pub elapsed: f32,
+    pub splits: Vec<Split>,
+    /// A console command changed the run, set by the developer console.
+    pub console_used: bool,
+}
+
+/// What a run counts for, see [`RunTimer::standing`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Standing {
+    /// Counts for best times, ghosts and splits.
+    Clean,
+    /// Made easier by [`Assists`] or `--adaptive`, counts for the assisted best times and splits.
+    Assisted,
+    /// Used cheats, practice mode or the console, or did not start at the first level. Nothing
+    /// of it is kept.
+    Unranked,
+}
+
+impl RunTimer {
+    /// The one place deciding whether a run is kept, for best times, ghosts and splits alike.
+    pub fn standing(&self, opts: &Opts, assists: &Assists) -> Standing {
+        let cheated = opts.god_mode || opts.noclip || opts.no_timer || opts.practice;
+        if cheated || opts.start_level() != 0 || self.console_used {
+            Standing::Unranked
+        } else if assists.any() || opts.adaptive {
+            Standing::Assisted
+        } else {
+            Standing::Clean
+        }
+    }
+
+    /// One line per split with the time of the level, the run time and the difference to `best`.
+    pub fn table(&self, best: Option<&[Split]>) -> String {
+        let mut previous = 0.0;
+        let mut lines = Vec::new();
+        for (i, split) in self.splits.iter().enumerate() {
+            let mut line = format!(
+                "Level {}: {} ({}), {} {}",
+                split.level + 1,
+                format_time(split.time - previous),
+                format_time(split.time),
+                split.deaths,
+                if split.deaths == 1 { "death" } else { "deaths" }
+            );
+            if let Some(pb) = best.and_then(|best| best.get(i)) {
+                line.push_str(&format!(
+                    ", {} to PB",
+                    format_difference(split.time - pb.time)
+                ));
+            }
+            lines.push(line);
+            previous = split.time;
+        }
+        lines.join("\n")
+    }
+}
+
+/// `m:ss.mmm`, or `h:mm:ss.mmm` for runs over an hour.
+pub fn format_time(seconds: f32) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    let (hours, minutes, seconds, millis) = (
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000,
+    );
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}.{millis:03}")
+    } else {
+        format!("{minutes}:{seconds:02}.{millis:03}")
+    }
+}
+
+fn format_difference(seconds: f32) -> String {
+    let sign = if seconds < 0.0 { '-' } else { '+' };
+    format!("{sign}{}", format_time(seconds.abs()))
+}
+
+/// Whether the run timer is shown, toggled with T.
+#[derive(Resource)]
+pub struct ShowRunTimer(pub bool);
+
+fn reset_run_timer(mut timer: ResMut<RunTimer>) {
+    *timer = RunTimer::default();
+}
+
+fn tick_run_timer(mut timer: ResMut<RunTimer>, time: Res<Time<Real>>) {
+    timer.elapsed += time.delta_secs();
+}
+
+fn split(mut timer: ResMut<RunTimer>, current_level: Res<CurrentLevel>, deaths: Res<DeathCounter>) {
+    let split = Split {
+        level: current_level.0,
+        time: timer.elapsed,
+        deaths: deaths.0.get(&current_level.0).copied().unwrap_or_default(),
+    };
+    timer.splits.push(split);
+}
+
+fn toggle_run_timer(keys: Res<ButtonInput<KeyCode>>, mut show: ResMut<ShowRunTimer>) {
+    if keys.just_pressed(KeyCode::KeyT) {
+        show.0 = !show.0;
+    }
+}
+
+#[derive(Component)]
+struct RunTimerText;
+
+fn spawn_run_timer_text(mut commands: Commands, assets: Res<RequiredAssets>) {
+    commands.spawn((
+        DespawnOnExit(Screen::Gameplay),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: px(20),
+            right: px(100),
+            ..Default::default()
+        },
+        children![(
+            Text::new(""),
+            TextFont {
+                font: assets.font.clone().unwrap(),
+                ..Default::default()
+            },
+            RunTimerText
+        )],
+    ));
+}
+
+fn update_run_timer_text(
+    mut text: Single<(&mut Text, &mut Visibility), With<RunTimerText>>,
+    timer: Res<RunTimer>,
+    show: Res<ShowRunTimer>,
+) {
+    let (text, visibility) = &mut *text;
+    **visibility = if show.0 {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+    text.0 = format_time(timer.elapsed);
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    fn standing(args: &[&str], assists: Assists, console_used: bool) -> Standing {
+        let opts = Opts::parse_from(std::iter::once("dornburg").chain(args.iter().copied()));
+        let run = RunTimer {
+            console_used,
+            ..Default::default()
+        };
+        run.standing(&opts, &assists)
+    }
+
+    #[test]
+    fn only_unaided_runs_from_the_start_are_clean() {
+        let slow = Assists {
+            game_speed: 0.5,
+            ..Default::default()
+        };
+        assert_eq!(standing(&[], Assists::default(), false), Standing::Clean);
+        assert_eq!(
+            standing(&["--level", "1"], Assists::default(), false),
+            Standing::Clean
+        );
+        assert_eq!(standing(&[], slow, false), Standing::Assisted);
+        assert_eq!(
+            standing(&["--adaptive"], Assists::default(), false),
+            Standing::Assisted
+        );
+        for cheat in [
+            &["--god-mode"][..],
+            &["--noclip"],
+            &["--no-timer"],
+            &["--practice"],
+            &["--level", "2"],
+        ] {
+            assert_eq!(
+                standing(cheat, slow, false),
+                Standing::Unranked,
+                "{cheat:?}"
+            );
+        }
+        assert_eq!(standing(&[], Assists::default(), true), Standing::Unranked);
+    }
+}
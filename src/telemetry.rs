@@ -0,0 +1,238 @@
+use std::{io::Write, path::Path};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Opts,
+    death::DeathCause,
+    difficulty::Difficulty,
+    levels::{CurrentLevel, LevelScreens},
+    player::PlayerMarker,
+    save::SaveFile,
+    terrain::{RequiredFinishes, TerrainSeed},
+};
+
+/// Seconds between two samples of the player path.
+pub const SAMPLE_INTERVAL: f32 = 0.25;
+
+/// Keeps a local log of how level attempts went, once enabled in the settings.
+///
+/// Every attempt is appended to `--telemetry` as one line of RON, `dornburg heatmap` turns the
+/// log into a picture per level. Nothing leaves the machine.
+pub struct TelemetryPlugin;
+
+impl Plugin for TelemetryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Telemetry>();
+        app.add_systems(OnEnter(LevelScreens::Level), start_attempt);
+        app.add_systems(Update, sample_path.run_if(in_state(LevelScreens::Level)));
+        app.add_systems(OnExit(LevelScreens::Level), log_attempt);
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum Outcome {
+    Finished,
+    Died {
+        cause: DeathCause,
+        position: [f32; 2],
+    },
+    /// Restarted from the console, left for the menu or the game was closed.
+    Abandoned,
+}
+
+/// One attempt at a level, a line of the telemetry log.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Attempt {
+    /// Index into [`crate::RequiredAssets::levels`].
+    pub level: u32,
+    pub difficulty: Difficulty,
+    pub seed: u64,
+    pub seconds: f32,
+    pub outcome: Outcome,
+    /// Seconds into the attempt each bone was collected at.
+    pub bones: Vec<f32>,
+    /// Player positions every [`SAMPLE_INTERVAL`] seconds.
+    pub path: Vec<[f32; 2]>,
+}
+
+/// The attempt being recorded, `None` while telemetry is off.
+#[derive(Resource, Default)]
+pub struct Telemetry {
+    attempt: Option<Attempt>,
+    next_sample: f32,
+}
+
+impl Telemetry {
+    /// Records how the attempt ended, only the first death of an attempt counts.
+    pub fn died(&mut self, cause: DeathCause, position: Vec2) {
+        if let Some(attempt) = &mut self.attempt
+            && attempt.outcome == Outcome::Abandoned
+        {
+            attempt.outcome = Outcome::Died {
+                cause,
+                position: position.into(),
+            };
+        }
+    }
+
+    pub fn collected_bone(&mut self) {
+        if let Some(attempt) = &mut self.attempt {
+            attempt.bones.push(attempt.seconds);
+        }
+    }
+}
+
+/// Appends `attempt` to the log at `path`.
+pub fn append(path: &Path, attempt: &Attempt) -> Result<(), String> {
+    let line = ron::to_string(attempt).map_err(|e| e.to_string())?;
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| writeln!(file, "{line}"))
+        .map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// All attempts in the log at `path`, oldest first.
+pub fn read_log(path: &Path) -> Result<Vec<Attempt>, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            ron::from_str(line).map_err(|e| format!("{}:{}: {e}", path.display(), i + 1))
+        })
+        .collect()
+}
+
+/// Replays would log the attempts they were recorded from a second time.
+fn start_attempt(
+    mut telemetry: ResMut<Telemetry>,
+    opts: Res<Opts>,
+    save: Res<SaveFile>,
+    current_level: Res<CurrentLevel>,
+    difficulty: Res<Difficulty>,
+    seed: Res<TerrainSeed>,
+) {
+    telemetry.next_sample = 0.0;
+    telemetry.attempt = (save.data.settings.telemetry && opts.replay.is_none()).then(|| Attempt {
+        level: current_level.0,
+        difficulty: *difficulty,
+        seed: seed.0,
+        seconds: 0.0,
+        outcome: Outcome::Abandoned,
+        bones: Vec::new(),
+        path: Vec::new(),
+    });
+}
+
+fn sample_path(
+    mut telemetry: ResMut<Telemetry>,
+    player: Query<&Transform, With<PlayerMarker>>,
+    time: Res<Time>,
+) {
+    let Telemetry {
+        attempt: Some(attempt),
+        next_sample,
+    } = &mut *telemetry
+    else {
+        return;
+    };
+    attempt.seconds += time.delta_secs();
+    if attempt.seconds < *next_sample {
+        return;
+    }
+    if let Ok(player) = player.single() {
+        attempt.path.push(player.translation.xy().into());
+        *next_sample += SAMPLE_INTERVAL;
+    }
+}
+
+fn log_attempt(
+    mut telemetry: ResMut<Telemetry>,
+    opts: Res<Opts>,
+    required_finishes: Res<RequiredFinishes>,
+) {
+    let Some(mut attempt) = telemetry.attempt.take() else {
+        return;
+    };
+    if required_finishes.0 == 0 && attempt.outcome == Outcome::Abandoned {
+        attempt.outcome = Outcome::Finished;
+    }
+    if let Err(e) = append(&opts.telemetry, &attempt) {
+        warn!("{e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn appends_one_attempt_per_line() {
+        let attempts = [
+            Attempt {
+                level: 0,
+                difficulty: Difficulty::Wanderer,
+                seed: 0,
+                seconds: 3.5,
+                outcome: Outcome::Died {
+                    cause: DeathCause::Lava,
+                    position: [-120.0, 40.0],
+                },
+                bones: vec![],
+                path: vec![[0.0, 0.0], [5.0, -2.5]],
+            },
+            Attempt {
+                level: 2,
+                difficulty: Difficulty::Nightmare,
+                seed: 9,
+                seconds: 41.0,
+                outcome: Outcome::Finished,
+                bones: vec![12.25, 40.75],
+                path: vec![[10.0, 10.0]],
+            },
+        ];
+        let dir = TempDir::new("telemetry");
+        let path = dir.join("telemetry.ron");
+        for attempt in &attempts {
+            append(&path, attempt).unwrap();
+        }
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content.lines().count(), 2);
+        assert_eq!(read_log(&path), Ok(attempts.to_vec()));
+    }
+
+    #[test]
+    fn the_first_death_counts() {
+        let mut telemetry = Telemetry::default();
+        telemetry.died(DeathCause::Lava, Vec2::ZERO);
+        assert!(telemetry.attempt.is_none());
+        telemetry.attempt = Some(Attempt {
+            level: 0,
+            difficulty: Difficulty::Wanderer,
+            seed: 0,
+            seconds: 2.0,
+            outcome: Outcome::Abandoned,
+            bones: vec![],
+            path: vec![],
+        });
+        telemetry.collected_bone();
+        telemetry.died(DeathCause::OutOfBounds, Vec2::new(0.0, -2000.0));
+        telemetry.died(DeathCause::TimeUp, Vec2::ZERO);
+        let attempt = telemetry.attempt.unwrap();
+        assert_eq!(attempt.bones, vec![2.0]);
+        assert_eq!(
+            attempt.outcome,
+            Outcome::Died {
+                cause: DeathCause::OutOfBounds,
+                position: [0.0, -2000.0],
+            }
+        );
+    }
+}
@@ -1,19 +1,30 @@
 use core::f32;
 
+use std::{collections::VecDeque, time::Duration};
+
 use avian2d::prelude::*;
 use bevy::{
     asset::RenderAssetUsages,
-    color::color_difference::EuclideanDistance,
     image::ImageSampler,
+    platform::time::Instant,
     prelude::*,
     render::render_resource::{AsBindGroup, Extent3d},
     sprite_render::Material2d,
+    tasks::{ComputeTaskPool, TaskPool},
 };
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    RequiredAssets,
+    Opts, RequiredAssets,
+    anchor::spawn_anchor,
+    assist::Assists,
+    death::{DeathCause, PlayerDeath},
+    difficulty::Difficulty,
+    layout::LevelLayout,
     levels::{CurrentLevel, LevelScreens},
     player::PlayerMarker,
+    telemetry::Telemetry,
+    transition::{Transition, TransitionStyle},
 };
 
 /// A level is initialized from an image.
@@ -22,6 +33,7 @@ use crate::{
 /// 566C86: Spawn
 /// 73EFF7: End/Checkpoint
 /// B13E53: Killzone
+/// FFCD75: Lantern, see [`crate::anchor::TimeAnchor`]
 ///
 /// The image is transformed into a mesh, with 1 vertex per pixel
 /// adjacent vertices are conencted into triangles
@@ -44,45 +56,35 @@ pub fn spawn_level(
     asset_server: Res<AssetServer>,
     mut required_finishes: ResMut<RequiredFinishes>,
     current_level: Res<CurrentLevel>,
+    difficulty: Res<Difficulty>,
 ) {
     let level = images
         .get(&required.levels[current_level.0 as usize])
         .unwrap();
-    if level.width() != 128 {
-        panic!("levels must be 128p wide");
-    }
-    if level.height() != 128 {
-        panic!("levels must be 128p high");
-    }
-    let terrain = Color::Srgba(Srgba::hex("#1A1C2C").unwrap());
-    let kill = Color::Srgba(Srgba::hex("#B13E53").unwrap());
-    let finish = Color::Srgba(Srgba::hex("#73EFF7").unwrap());
-    let spawn_color = Color::Srgba(Srgba::hex("#566C86").unwrap());
-
-    let mut voxels = VoxelizedView::empty();
-    let mut killzones = Killzones::empty();
-    let mut finishes = Vec::new();
-    let mut finish_coords = Vec::new();
-    let mut spawn = Vec2::ZERO;
-
-    for y in 0..level.height() {
-        for x in 0..level.width() {
-            if let Ok(color) = level.get_color_at(x, y) {
-                voxels.set(x, y, color.distance(&terrain) <= 0.0001);
-                killzones.set(x, y, color.distance(&kill) <= 0.0001);
-                if color.distance(&finish) < 0.0001 {
-                    finishes.push(voxel_to_world(x, y));
-                    finish_coords.push((x, y));
-                }
-                if color.distance(&spawn_color) <= 0.0001 {
-                    spawn = voxel_to_world(x, y);
-                }
-            }
-        }
+    commands.insert_resource(LastGeneration::default());
+    let layout = LevelLayout::parse(level).unwrap_or_else(|problem| panic!("{problem}"));
+    let finishes: Vec<Vec2> = layout
+        .finishes
+        .iter()
+        .map(|&(x, y)| voxel_to_world(x, y))
+        .collect();
+    let spawn = layout
+        .spawn()
+        .map(|(x, y)| voxel_to_world(x, y))
+        .unwrap_or(Vec2::ZERO);
+    for &(x, y) in &layout.anchors {
+        spawn_anchor(
+            &mut commands,
+            &mut meshes,
+            &mut colors,
+            cell_center(UVec2::new(x, y)),
+        );
     }
-    voxels.finish_coords = finish_coords;
+    let voxels = layout.voxels;
+    let killzones = layout.killzones;
 
-    let time = TimeDiluationMap::zero();
+    let mut time = TimeDiluationMap::for_level(current_level.0);
+    time.bubble = difficulty.bubble();
 
     let mut spawn_command = commands.spawn((
         DespawnOnExit(LevelScreens::Level),
@@ -99,7 +101,12 @@ pub fn spawn_level(
         })),
         voxels.clone(),
         time,
-        UpdateTimer(Timer::from_seconds(2.2, TimerMode::Repeating)),
+        UpdateTimer(Timer::from_seconds(
+            difficulty.update_interval(),
+            TimerMode::Repeating,
+        )),
+        TerrainHistory::default(),
+        LevelMemory::for_level(&voxels, current_level.0),
     ));
 
     if let Some(collider) = voxels.collider() {
@@ -116,26 +123,16 @@ pub fn spawn_level(
         kill_spawn.insert(collider);
     }
 
-    let finish_texture: Handle<Image> = asset_server.load("sprites/schaedel.png");
-    let finish_mat = colors.add(ColorMaterial {
-        texture: Some(finish_texture),
-        color: Color::WHITE,
-        alpha_mode: Default::default(),
-        uv_transform: Default::default(),
-    });
+    let finish_mesh = meshes.add(Rectangle::new(20.0, 20.0));
+    let finish_mat = finish_material(&mut colors, &asset_server);
     required_finishes.0 = finishes.len() as u32;
     for finish in finishes {
-        commands
-            .spawn((
-                DespawnOnExit(LevelScreens::Level),
-                Mesh2d(meshes.add(Rectangle::new(20.0, 20.0))),
-                MeshMaterial2d(finish_mat.clone()),
-                CollisionEventsEnabled,
-                Collider::rectangle(20.0, 20.0),
-                Transform::from_translation(Vec3::new(finish.x + 10.0, finish.y + 10.0, 0.0)),
-                FinishMarker,
-            ))
-            .observe(collect_finish);
+        spawn_finish(
+            &mut commands,
+            finish_mesh.clone(),
+            finish_mat.clone(),
+            finish + Vec2::splat(10.0),
+        );
     }
 
     commands.spawn((
@@ -148,39 +145,37 @@ pub fn spawn_level(
 #[derive(Component)]
 pub struct SpawnMarker;
 
-/// A circle of radius 8 blocks (160p) should not change
-/// the area from 8-10 blocks (160p - 200p) shows crater than 1s/s change
-/// further blocks show 1s/s change
-/// https://graphtoy.com/?f1(x,t)=clamp((x%5E2/160-160)/45,0,1)&v1=true&f2(x,t)=4/(1+f1(x,t))-1&v2=true&f3(x,t)=min(f1(x,t)*3,f2(x,t))&v3=true&f4(x,t)=&v4=false&f5(x,t)=&v5=false&f6(x,t)=&v6=false&grid=1&coords=165.74778969058985,-0.9241138897409666,12.000000000000151
+/// How long the last runs of the terrain systems took.
+#[derive(Resource, Default)]
+pub struct TerrainTimings {
+    pub update_time: Duration,
+    pub update_terrain: Duration,
+}
+
+/// Voxels that changed in the last generation of the terrain.
+#[derive(Resource, Default)]
+pub struct LastGeneration {
+    pub born: Vec<UVec2>,
+    pub killed: Vec<UVec2>,
+}
+
+/// While set, the terrain does not evolve.
+#[derive(Resource, Default)]
+pub struct TerrainFrozen(pub bool);
+
 pub fn update_time(
     player: Single<&Transform, With<PlayerMarker>>,
     mut times: Query<&mut TimeDiluationMap>,
     clock: Res<Time>,
+    mut timings: ResMut<TerrainTimings>,
 ) {
+    let start = Instant::now();
     let p = player.translation.xy();
     let d = clock.delta_secs();
     for mut time in &mut times {
-        let mut min_time = f32::INFINITY;
-        let mut zero_coords = Vec::new();
-        for x in 0..128 {
-            for y in 0..128 {
-                let voxel_position = voxel_to_world(x, y);
-                let z = p.distance_squared(voxel_position);
-                let f1 = (z / 260.0 - 260.0).clamp(0.0, 1.0);
-                let f2 = 4.0 / (1.0 + f1) - 1.0;
-                let f3 = (f1 * 3.0).min(f2);
-                time.tick(x, y, d * f3);
-                if f3 <= 0.00001 {
-                    let z = time.get(x, y);
-                    min_time = min_time.min(z + d * f3);
-                    zero_coords.push((x, y));
-                }
-            }
-        }
-        for (x, y) in zero_coords {
-            time.set(x, y, min_time);
-        }
+        time.advance(p, d);
     }
+    timings.update_time = start.elapsed();
 }
 
 #[derive(Component)]
@@ -189,20 +184,54 @@ pub struct FinishMarker;
 #[derive(Resource)]
 pub struct RequiredFinishes(pub u32);
 
+pub fn finish_material(
+    colors: &mut Assets<ColorMaterial>,
+    asset_server: &AssetServer,
+) -> Handle<ColorMaterial> {
+    colors.add(ColorMaterial {
+        texture: Some(asset_server.load("sprites/schaedel.png")),
+        color: Color::WHITE,
+        alpha_mode: Default::default(),
+        uv_transform: Default::default(),
+    })
+}
+
+/// A bone to collect, centered on `position`.
+pub fn spawn_finish(
+    commands: &mut Commands,
+    mesh: Handle<Mesh>,
+    material: Handle<ColorMaterial>,
+    position: Vec2,
+) {
+    commands
+        .spawn((
+            DespawnOnExit(LevelScreens::Level),
+            Mesh2d(mesh),
+            MeshMaterial2d(material),
+            CollisionEventsEnabled,
+            Collider::rectangle(20.0, 20.0),
+            Transform::from_translation(position.extend(0.0)),
+            FinishMarker,
+        ))
+        .observe(collect_finish);
+}
+
 fn collect_finish(
     event: On<CollisionStart>,
     player: Single<Entity, With<PlayerMarker>>,
-    mut next: ResMut<NextState<LevelScreens>>,
+    mut transition: ResMut<Transition>,
     mut commands: Commands,
     mut required_finishes: ResMut<RequiredFinishes>,
+    mut telemetry: ResMut<Telemetry>,
 ) {
     if event.collider2.entity() == player.into_inner() {
         commands.entity(event.collider1.entity()).despawn();
+        telemetry.collected_bone();
         if required_finishes.0 > 0 {
             required_finishes.0 -= 1;
         }
         if required_finishes.0 == 0 {
-            next.set(LevelScreens::Intermission);
+            transition.start(LevelScreens::Intermission, TransitionStyle::Iris);
         }
     }
 }
@@ -210,138 +239,140 @@ fn collect_finish(
 fn player_dies(
     event: On<CollisionStart>,
     player: Single<Entity, With<PlayerMarker>>,
-    mut next: ResMut<NextState<LevelScreens>>,
+    mut deaths: MessageWriter<PlayerDeath>,
+    opts: Res<Opts>,
+    assists: Res<Assists>,
 ) {
     let e = event.body2.unwrap();
-    if e == player.into_inner() {
-        next.set(LevelScreens::Restart);
+    if e == player.into_inner() && !opts.god_mode && !assists.invincible {
+        deaths.write(PlayerDeath {
+            cause: DeathCause::Lava,
+        });
     }
 }
 
 pub fn out_of_bounds(
     player: Single<&Transform, With<PlayerMarker>>,
-    mut next: ResMut<NextState<LevelScreens>>,
+    mut deaths: MessageWriter<PlayerDeath>,
+    opts: Res<Opts>,
+    difficulty: Res<Difficulty>,
 ) {
-    if player.translation.x < -20.0 * 65.0
-        || player.translation.x > 20.0 * 65.0
-        || player.translation.y < -20.0 * 65.0
-        || player.translation.y > 20.0 * 65.0
+    if opts.god_mode {
+        return;
+    }
+    let bounds = difficulty.bounds();
+    if player.translation.x < -bounds
+        || player.translation.x > bounds
+        || player.translation.y < -bounds
+        || player.translation.y > bounds
     {
-        next.set(LevelScreens::Restart);
+        deaths.write(PlayerDeath {
+            cause: DeathCause::OutOfBounds,
+        });
     }
 }
 
-fn voxel_to_world(x: u32, y: u32) -> Vec2 {
+pub fn voxel_to_world(x: u32, y: u32) -> Vec2 {
     Vec2::new(x as f32 - 64.0, -(y as f32) + 63.0) * 20.0
 }
 
+/// The world position of the center of a voxel, where the player spawns on a spawn pixel.
+pub fn cell_center(cell: UVec2) -> Vec2 {
+    voxel_to_world(cell.x, cell.y) + Vec2::splat(10.0)
+}
+
+/// The voxel containing a world position, if it is inside the level.
+pub fn world_to_voxel(p: Vec2) -> Option<UVec2> {
+    let x = (p.x / 20.0).floor() as i32 + 64;
+    let y = 63 - (p.y / 20.0).floor() as i32;
+    ((0..128).contains(&x) && (0..128).contains(&y)).then(|| UVec2::new(x as u32, y as u32))
+}
+
+/// How far the player can leave the level in every direction before it restarts, one voxel.
+pub const OUT_OF_BOUNDS: f32 = 20.0 * 65.0;
+/// Seconds between two generations of the terrain.
+pub const UPDATE_INTERVAL: f32 = 2.2;
+/// Voxels closer than this to the player do not change.
+pub const PROTECTION_RADIUS: f32 = 300.0;
+/// Voxels closer than this to the player do not advance in time.
+pub const TIME_BUBBLE_RADIUS: f32 = 260.0;
+/// Below this many set voxels, the terrain grows (10% of the level).
+pub const GROW_THRESHOLD: u32 = 1638;
+/// Above this many set voxels, the terrain shrinks (30% of the level).
+pub const SHRINK_THRESHOLD: u32 = 4915;
+
 pub fn update_terrain(
     mut commands: Commands,
     mut terrain: Query<(
         Entity,
         &mut VoxelizedView,
-        &mut MeshMaterial2d<TerrainMaterial>,
         &TimeDiluationMap,
         &Transform,
         &mut UpdateTimer,
+        &mut TerrainHistory,
+        &LevelMemory,
     )>,
-    mut materials: ResMut<Assets<TerrainMaterial>>,
-    mut images: ResMut<Assets<Image>>,
-    killzones: Single<&Killzones>,
     player: Single<&Transform, With<PlayerMarker>>,
     global_time: Res<Time>,
     current_level: Res<CurrentLevel>,
-    finishes: Query<&Transform, With<FinishMarker>>,
+    seed: Res<TerrainSeed>,
+    frozen: Res<TerrainFrozen>,
+    mut timings: ResMut<TerrainTimings>,
+    mut last_generation: ResMut<LastGeneration>,
 ) {
+    let start = Instant::now();
     let p = player.translation.xy();
-    let finishes: Vec<Vec2> = finishes.iter().map(|v| v.translation.xy()).collect();
-    for (entity, mut voxels, mut mat, time, transform, mut timer) in &mut terrain {
-        timer.0.tick(global_time.delta());
+    for (entity, mut voxels, time, transform, mut timer, mut history, memory) in &mut terrain {
+        if !frozen.0 {
+            timer.0.tick(global_time.delta());
+        }
         if timer.0.just_finished() {
-            let total = voxels.total();
-            let grow = total < 1638;
-            let shrink = total > 4915;
-            let mut new_voxels = voxels.clone();
+            history.push(&voxels, time);
+            let next = evolve(
+                &voxels,
+                time,
+                memory,
+                current_level.0,
+                seed.0,
+                p - transform.translation.xy(),
+            );
+            last_generation.born.clear();
+            last_generation.killed.clear();
             for x in 0..128 {
-                let x_f = x as f32 / 128.0;
                 for y in 0..128 {
-                    let voxel_position = voxel_to_world(x, y) + transform.translation.xy();
-                    if p.distance_squared(voxel_position) < 300.0 * 300.0 {
-                        continue;
-                    }
-                    let y_f = y as f32 / 128.0;
-                    let time = time.get(x, y);
-
-                    match current_level.0 {
-                        0 => {
-                            update_level1(
-                                time,
-                                &voxels,
-                                grow,
-                                shrink,
-                                &mut new_voxels,
-                                x,
-                                x_f,
-                                y,
-                                y_f,
-                            );
-                        }
-                        1 => {
-                            update_level2(
-                                time,
-                                &voxels,
-                                grow,
-                                shrink,
-                                &mut new_voxels,
-                                x,
-                                x_f,
-                                y,
-                                y_f,
-                            );
-                        }
-                        2 => {
-                            update_level3(
-                                time,
-                                &voxels,
-                                grow,
-                                shrink,
-                                &mut new_voxels,
-                                x,
-                                x_f,
-                                y,
-                                y_f,
-                            );
-                        }
-                        3 => {
-                            update_level4(
-                                time,
-                                &voxels,
-                                grow,
-                                shrink,
-                                &mut new_voxels,
-                                x,
-                                x_f,
-                                y,
-                                y_f,
-                            );
-                        }
+                    match (voxels.get(x, y), next.get(x, y)) {
+                        (false, true) => last_generation.born.push(UVec2::new(x, y)),
+                        (true, false) => last_generation.killed.push(UVec2::new(x, y)),
                         _ => (),
                     }
-
-                    // new_voxels.set(x, y, n > 4.4);
                 }
             }
-            *voxels = new_voxels;
-            if let Some(collider) = voxels.collider() {
-                commands
-                    .get_entity(entity)
-                    .unwrap()
-                    .remove::<Collider>()
-                    .insert(collider);
-            } else {
-                commands.get_entity(entity).unwrap().remove::<Collider>();
-            }
+            *voxels = next;
+            replace_collider(&mut commands, entity, voxels.collider());
         }
+    }
+    timings.update_terrain = start.elapsed();
+}
+
+/// Uploads the terrain, time field and killzones for the shader, kept apart from
+/// [`update_terrain`] so the level can be simulated without rendering.
+pub fn update_terrain_material(
+    mut terrain: Query<(
+        &VoxelizedView,
+        &mut MeshMaterial2d<TerrainMaterial>,
+        &TimeDiluationMap,
+    )>,
+    mut materials: ResMut<Assets<TerrainMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+    killzones: Single<&Killzones>,
+    player: Single<&Transform, With<PlayerMarker>>,
+    global_time: Res<Time>,
+    current_level: Res<CurrentLevel>,
+    finishes: Query<&Transform, With<FinishMarker>>,
+) {
+    let p = player.translation.xy();
+    let finishes: Vec<Vec2> = finishes.iter().map(|v| v.translation.xy()).collect();
+    for (voxels, mut mat, time) in &mut terrain {
         let f1 = finishes
             .first()
             .cloned()
@@ -366,10 +397,201 @@ pub fn update_terrain(
     }
 }
 
+pub fn replace_collider(commands: &mut Commands, entity: Entity, collider: Option<Collider>) {
+    if let Some(collider) = collider {
+        commands
+            .get_entity(entity)
+            .unwrap()
+            .remove::<Collider>()
+            .insert(collider);
+    } else {
+        commands.get_entity(entity).unwrap().remove::<Collider>();
+    }
+}
+
+/// Generations kept for rewinding, 16 generations are about 35s.
+pub const HISTORY_LENGTH: usize = 16;
+
+/// The terrain and time field before each of the last generations, newest last.
+#[derive(Component, Default)]
+pub struct TerrainHistory(pub VecDeque<(VoxelizedView, TimeDiluationMap)>);
+
+impl TerrainHistory {
+    pub fn push(&mut self, voxels: &VoxelizedView, time: &TimeDiluationMap) {
+        if self.0.len() == HISTORY_LENGTH {
+            self.0.pop_front();
+        }
+        self.0.push_back((voxels.clone(), time.clone()));
+    }
+}
+
+/// Offsets the noise of the terrain rules, so the same level evolves differently.
+#[derive(Resource, Clone, Copy)]
+pub struct TerrainSeed(pub u64);
+
+/// Where in the noise domain a seed starts, seed 0 keeps the noise the levels were designed with.
+pub fn seed_offset(seed: u64) -> Vec3 {
+    if seed == 0 {
+        return Vec3::ZERO;
+    }
+    let mut state = seed;
+    Vec3::new(
+        (splitmix64(&mut state) % 4096) as f32 / 64.0,
+        (splitmix64(&mut state) % 4096) as f32 / 64.0,
+        (splitmix64(&mut state) % 4096) as f32 / 64.0,
+    )
+}
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Seconds of local time after which the memory of a voxel pulls at full strength.
+pub const MEMORY_RAMP: f32 = 30.0;
+
+/// The layout from the level image, the CA pulls voxels back towards it so key routes return.
+#[derive(Component, Clone)]
+pub struct LevelMemory {
+    pub authored: VoxelizedView,
+    /// Chance per generation that a voxel which differs from the level image returns to it, once
+    /// [`MEMORY_RAMP`] seconds of local time passed. 0 turns the memory off.
+    pub strength: f32,
+}
+
+impl LevelMemory {
+    pub fn for_level(authored: &VoxelizedView, level: u32) -> LevelMemory {
+        LevelMemory {
+            authored: authored.clone(),
+            strength: LevelMemory::strength_for_level(level),
+        }
+    }
+
+    /// Only level 2 relies on its gap coming back.
+    pub fn strength_for_level(level: u32) -> f32 {
+        match level {
+            1 => 0.1,
+            _ => 0.0,
+        }
+    }
+
+    /// Rolls whether voxel `x`, `y` returns to the level image this generation, the roll only
+    /// depends on the voxel, its local time and the seed.
+    fn pulls_back(&self, x: u32, y: u32, local_time: f32, seed: u64) -> bool {
+        let weight = (local_time / MEMORY_RAMP).clamp(0.0, 1.0);
+        let mut state = seed ^ ((x as u64) << 7 | y as u64) ^ ((local_time.to_bits() as u64) << 14);
+        let roll = (splitmix64(&mut state) >> 40) as f32 / (1u64 << 24) as f32;
+        roll < self.strength * weight
+    }
+}
+
+/// Runs one generation of the cellular automaton of a level.
+/// `player` is relative to the terrain, voxels around it are protected.
+pub fn evolve(
+    voxels: &VoxelizedView,
+    time: &TimeDiluationMap,
+    memory: &LevelMemory,
+    level: u32,
+    seed: u64,
+    player: Vec2,
+) -> VoxelizedView {
+    let total = voxels.total();
+    let grow = total < GROW_THRESHOLD;
+    let shrink = total > SHRINK_THRESHOLD;
+    let offset = seed_offset(seed);
+    let counts = voxels.neighbor_counts();
+    let mut new_voxels = voxels.clone();
+    for x in 0..128 {
+        let x_f = x as f32 / 128.0 + offset.x;
+        for y in 0..128 {
+            if time.holds(player, voxel_to_world(x, y)) {
+                continue;
+            }
+            let y_f = y as f32 / 128.0 + offset.y;
+            let local_time = time.get(x, y);
+            let time = local_time + offset.z;
+
+            match level {
+                0 => {
+                    update_level1(
+                        time,
+                        voxels,
+                        &counts,
+                        grow,
+                        shrink,
+                        &mut new_voxels,
+                        x,
+                        x_f,
+                        y,
+                        y_f,
+                    );
+                }
+                1 => {
+                    update_level2(
+                        time,
+                        voxels,
+                        &counts,
+                        grow,
+                        shrink,
+                        &mut new_voxels,
+                        x,
+                        x_f,
+                        y,
+                        y_f,
+                    );
+                }
+                2 => {
+                    update_level3(
+                        time,
+                        voxels,
+                        &counts,
+                        grow,
+                        shrink,
+                        &mut new_voxels,
+                        x,
+                        x_f,
+                        y,
+                        y_f,
+                    );
+                }
+                3 => {
+                    update_level4(
+                        time,
+                        voxels,
+                        &counts,
+                        grow,
+                        shrink,
+                        &mut new_voxels,
+                        x,
+                        x_f,
+                        y,
+                        y_f,
+                    );
+                }
+                _ => (),
+            }
+
+            // new_voxels.set(x, y, n > 4.4);
+
+            if memory.strength > 0.0 {
+                let authored = memory.authored.get(x, y);
+                if new_voxels.get(x, y) != authored && memory.pulls_back(x, y, local_time, seed) {
+                    new_voxels.set(x, y, authored);
+                }
+            }
+        }
+    }
+    new_voxels
+}
+
 // feels good enough for the expected duration to finish level 1, the end gets harder to reach as time goes on
 fn update_level1(
     local_time: f32,
     voxels: &VoxelizedView,
+    counts: &NeighborCounts,
     grow: bool,
     shrink: bool,
     new_voxels: &mut VoxelizedView,
@@ -378,7 +600,7 @@ fn update_level1(
     y: u32,
     y_f: f32,
 ) {
-    let s = voxels.get_surrounding(x, y, 1);
+    let s = counts.get(x, y);
 
     let n = fbm(Vec3::new(x_f, y_f, local_time), 5, 20.0, 1.2, 0.6);
     let c = voxels.get(x, y);
@@ -405,6 +627,7 @@ fn update_level1(
 fn update_level2(
     local_time: f32,
     voxels: &VoxelizedView,
+    counts: &NeighborCounts,
     grow: bool,
     shrink: bool,
     new_voxels: &mut VoxelizedView,
@@ -413,7 +636,7 @@ fn update_level2(
     y: u32,
     y_f: f32,
 ) {
-    let s = voxels.get_surrounding(x, y, 1);
+    let s = counts.get(x, y);
 
     let n = fbm(Vec3::new(x_f, y_f, local_time), 5, 20.0, 1.2, 0.6);
     let c = voxels.get(x, y);
@@ -439,6 +662,7 @@ fn update_level2(
 fn update_level3(
     local_time: f32,
     voxels: &VoxelizedView,
+    counts: &NeighborCounts,
     grow: bool,
     shrink: bool,
     new_voxels: &mut VoxelizedView,
@@ -447,7 +671,7 @@ fn update_level3(
     y: u32,
     y_f: f32,
 ) {
-    let s = voxels.get_surrounding(x, y, 1);
+    let s = counts.get(x, y);
 
     let n = fbm(Vec3::new(x_f, y_f, local_time), 5, 20.0, 1.2, 0.6);
     let c = voxels.get(x, y);
@@ -473,6 +697,7 @@ fn update_level3(
 fn update_level4(
     local_time: f32,
     voxels: &VoxelizedView,
+    counts: &NeighborCounts,
     grow: bool,
     shrink: bool,
     new_voxels: &mut VoxelizedView,
@@ -481,7 +706,7 @@ fn update_level4(
     y: u32,
     y_f: f32,
 ) {
-    let s = voxels.get_surrounding(x, y, 1);
+    let s = counts.get(x, y);
 
     let n = fbm(Vec3::new(x_f, y_f, local_time), 5, 20.0, 1.2, 0.6);
     let c = voxels.get(x, y);
@@ -504,7 +729,7 @@ fn update_level4(
     }
 }
 
-fn dotnoise(mut x: Vec3) -> f32 {
+pub fn dotnoise(mut x: Vec3) -> f32 {
     let mut v = 0.0;
     for i in 0..4 {
         x = x
@@ -553,29 +778,30 @@ pub fn fbm(
     result
 }
 
-#[derive(Component)]
+#[derive(Component, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct UpdateTimer(pub Timer);
 
-#[derive(Component, Clone)]
+#[derive(Component, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct VoxelizedView {
+    #[serde(with = "grid")]
     voxels: Vec<u128>,
-    finish_coords: Vec<(u32, u32)>,
+    pub finish_coords: Vec<(u32, u32)>,
 }
 
 impl VoxelizedView {
-    fn empty() -> VoxelizedView {
+    pub fn empty() -> VoxelizedView {
         VoxelizedView {
             voxels: vec![0; 128],
             finish_coords: Vec::new(),
         }
     }
 
-    fn get(&self, x: u32, y: u32) -> bool {
+    pub fn get(&self, x: u32, y: u32) -> bool {
         assert!(x < 128 && y < 128);
         self.voxels[x as usize] & (1u128 << y) > 0
     }
 
-    fn get_checked(&self, mut x: i32, mut y: i32) -> bool {
+    pub fn get_checked(&self, mut x: i32, mut y: i32) -> bool {
         x = x.clamp(0, 127);
         y = y.clamp(0, 127);
 
@@ -583,7 +809,7 @@ impl VoxelizedView {
     }
 
     /// returns how many of the 9 pixels are set;
-    fn get_surrounding(&self, x: u32, y: u32, size: i32) -> u8 {
+    pub fn get_surrounding(&self, x: u32, y: u32, size: i32) -> u8 {
         let x = x as i32;
         let y = y as i32;
         let mut s = 0;
@@ -596,7 +822,12 @@ impl VoxelizedView {
         s
     }
 
-    fn set(&mut self, x: u32, y: u32, v: bool) {
+    /// [`VoxelizedView::get_surrounding`] with size 1 for all voxels at once.
+    pub fn neighbor_counts(&self) -> NeighborCounts {
+        NeighborCounts::new(&self.voxels, 1)
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, v: bool) {
         assert!(x < 128 && y < 128);
         if self.finish_coords.contains(&(x, y)) {
             return;
@@ -605,7 +836,7 @@ impl VoxelizedView {
         self.voxels[x as usize] = (self.voxels[x as usize] & !(1 << y)) | (v << y);
     }
 
-    fn total(&self) -> u32 {
+    pub fn total(&self) -> u32 {
         let mut c = 0;
         for x in 0..128 {
             for y in 0..128 {
@@ -615,7 +846,7 @@ impl VoxelizedView {
         c
     }
 
-    fn collider(&self) -> Option<Collider> {
+    pub fn collider(&self) -> Option<Collider> {
         let mut coordinates = Vec::new();
         for x in 0..128 {
             for y in 0..128 {
@@ -634,7 +865,7 @@ impl VoxelizedView {
         }
     }
 
-    fn as_tex(&self) -> Image {
+    pub fn as_tex(&self) -> Image {
         let mut height_bytes = Vec::new();
         for x in 0..128 {
             for y in 0..128 {
@@ -659,40 +890,278 @@ impl VoxelizedView {
     }
 }
 
-#[derive(Component)]
+/// How many of the 9 cells around every cell are set, with the borders clamped like
+/// [`VoxelizedView::get_checked`].
+///
+/// The grid is given as columns of `words` u128 each, bit `y % 128` of word `y / 128` is the cell
+/// at height `y`. The counts are kept as 4 bit planes per word and summed with bitwise full adders,
+/// so a 128 high column is handled in a handful of instructions instead of 128 * 9 lookups.
+pub struct NeighborCounts {
+    planes: Vec<[u128; 4]>,
+    words: usize,
+}
+
+impl NeighborCounts {
+    pub fn new(columns: &[u128], words: usize) -> NeighborCounts {
+        assert!(words > 0 && columns.len() % words == 0);
+        let width = columns.len() / words;
+        let column = |x: usize| &columns[x * words..(x + 1) * words];
+        // the 3 vertical neighbors of every cell of a column, shifted in from the adjacent words
+        let shifted = |c: &[u128], w: usize| {
+            let below = if w == 0 { c[0] & 1 } else { c[w - 1] >> 127 };
+            let above = if w + 1 == words {
+                c[w] & (1 << 127)
+            } else {
+                c[w + 1] << 127
+            };
+            [(c[w] << 1) | below, c[w], (c[w] >> 1) | above]
+        };
+
+        let mut planes = vec![[0; 4]; columns.len()];
+        for x in 0..width {
+            let neighbors = [x.saturating_sub(1), x, (x + 1).min(width - 1)];
+            for w in 0..words {
+                let sum = &mut planes[x * words + w];
+                for n in neighbors {
+                    for mut carry in shifted(column(n), w) {
+                        for plane in sum.iter_mut() {
+                            let next = *plane & carry;
+                            *plane ^= carry;
+                            carry = next;
+                        }
+                    }
+                }
+            }
+        }
+        NeighborCounts { planes, words }
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> u8 {
+        let planes = &self.planes[x as usize * self.words + y as usize / 128];
+        let bit = y % 128;
+        planes
+            .iter()
+            .enumerate()
+            .map(|(i, plane)| (((plane >> bit) & 1) as u8) << i)
+            .sum()
+    }
+}
+
+/// How the local time of a level evolves, see [`TimeDiluationMap::advance`].
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TimeFieldParams {
+    /// Seconds of local time per second, away from all sources and sinks.
+    pub baseline: f32,
+    /// How fast differences in local time spread to the neighboring voxels, in voxels² per second.
+    pub diffusion: f32,
+    /// The fraction of the lag behind the baseline clock a voxel catches up per second.
+    pub decay: f32,
+}
+
+impl TimeFieldParams {
+    /// `level` is the index into [`RequiredAssets::levels`].
+    pub fn for_level(level: u32) -> TimeFieldParams {
+        match level {
+            0 | 1 => TimeFieldParams {
+                baseline: 1.0,
+                diffusion: 1.0,
+                decay: 0.02,
+            },
+            2 => TimeFieldParams {
+                baseline: 1.1,
+                diffusion: 1.5,
+                decay: 0.03,
+            },
+            _ => TimeFieldParams {
+                baseline: 1.2,
+                diffusion: 2.0,
+                decay: 0.04,
+            },
+        }
+    }
+}
+
+/// Scales the rate of local time within `radius` of `position`, a `rate` of 0 stops time (a sink),
+/// above 1 it runs faster (a source). The effect fades out over the outer fifth of the radius.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TimeSource {
+    pub position: Vec2,
+    pub radius: f32,
+    pub rate: f32,
+}
+
+impl TimeSource {
+    fn factor(&self, p: Vec2) -> f32 {
+        let d = p.distance(self.position);
+        let t = ((d - self.radius * 0.8) / (self.radius * 0.2)).clamp(0.0, 1.0);
+        let fade = t * t * (3.0 - 2.0 * t);
+        self.rate + (1.0 - self.rate) * fade
+    }
+}
+
+/// The stasis around the player.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlayerBubble {
+    /// Voxels closer than this to the player do not advance in time.
+    pub time_radius: f32,
+    /// Voxels closer than this to the player do not change.
+    pub protection_radius: f32,
+}
+
+impl Default for PlayerBubble {
+    fn default() -> Self {
+        PlayerBubble {
+            time_radius: TIME_BUBBLE_RADIUS,
+            protection_radius: PROTECTION_RADIUS,
+        }
+    }
+}
+
+impl PlayerBubble {
+    pub fn scaled(self, factor: f32) -> PlayerBubble {
+        PlayerBubble {
+            time_radius: self.time_radius * factor,
+            protection_radius: self.protection_radius * factor,
+        }
+    }
+}
+
+/// The player is a sink stopping time within `radius`, [`TIME_BUBBLE_RADIUS`] by default.
+///
+/// A circle of radius 8 blocks (160p) should not change
+/// the area from 8-10 blocks (160p - 200p) shows crater than 1s/s change
+/// further blocks show 1s/s change
+/// https://graphtoy.com/?f1(x,t)=clamp((x%5E2/160-160)/45,0,1)&v1=true&f2(x,t)=4/(1+f1(x,t))-1&v2=true&f3(x,t)=min(f1(x,t)*3,f2(x,t))&v3=true&f4(x,t)=&v4=false&f5(x,t)=&v5=false&f6(x,t)=&v6=false&grid=1&coords=165.74778969058985,-0.9241138897409666,12.000000000000151
+fn bubble_factor(radius: f32, player: Vec2, p: Vec2) -> f32 {
+    let z = player.distance_squared(p);
+    let f1 = (z / radius - radius).clamp(0.0, 1.0);
+    let f2 = 4.0 / (1.0 + f1) - 1.0;
+    (f1 * 3.0).min(f2)
+}
+
+#[derive(Component, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct TimeDiluationMap {
     time: Vec<f32>,
+    size: u32,
+    /// Local time of a voxel far away from every source and sink.
+    clock: f32,
+    params: TimeFieldParams,
+    pub bubble: PlayerBubble,
+    /// Sources and sinks besides the player.
+    pub sources: Vec<TimeSource>,
 }
 
 impl TimeDiluationMap {
-    fn zero() -> TimeDiluationMap {
+    pub fn zero() -> TimeDiluationMap {
+        TimeDiluationMap::for_level(0)
+    }
+
+    pub fn for_level(level: u32) -> TimeDiluationMap {
+        TimeDiluationMap::with_size(128, TimeFieldParams::for_level(level))
+    }
+
+    /// A field of `size` x `size` voxels, centered like [`voxel_to_world`].
+    pub fn with_size(size: u32, params: TimeFieldParams) -> TimeDiluationMap {
         TimeDiluationMap {
-            time: vec![0.0; 128 * 128],
+            time: vec![0.0; (size * size) as usize],
+            size,
+            clock: 0.0,
+            params,
+            bubble: PlayerBubble::default(),
+            sources: Vec::new(),
         }
     }
 
-    fn tick(&mut self, x: u32, y: u32, d: f32) {
-        assert!(x < 128 && y < 128);
-        let i = x * 128 + y;
-        self.time[i as usize] += d;
+    /// Advances the field by `d` seconds on the [`ComputeTaskPool`].
+    pub fn advance(&mut self, player: Vec2, d: f32) {
+        self.advance_on(ComputeTaskPool::get_or_init(TaskPool::default), player, d);
     }
-    fn set(&mut self, x: u32, y: u32, d: f32) {
-        assert!(x < 128 && y < 128);
-        let i = x * 128 + y;
-        self.time[i as usize] = d;
+
+    /// Every voxel ticks at the baseline rate scaled by the player bubble and all sources, then
+    /// exchanges time with its 4 neighbors (the borders are closed) and is pulled towards the
+    /// baseline clock, so regions the player stood in slowly catch up again.
+    ///
+    /// The columns are split evenly between the threads of `pool`. Every voxel only reads the
+    /// field of the previous step, so the result does not depend on the number of threads.
+    pub fn advance_on(&mut self, pool: &TaskPool, player: Vec2, d: f32) {
+        let size = self.size as usize;
+        let half = (size / 2) as f32;
+        let params = self.params;
+        let clock = self.clock;
+        let sources = &self.sources;
+        let bubble = self.bubble.time_radius;
+        // explicit diffusion is only stable up to a quarter of the difference per step
+        let diffusion = (params.diffusion * d).min(0.25);
+        let decay = (params.decay * d).min(1.0);
+        let old = self.time.clone();
+        let old = &old;
+        let columns_per_task = size.div_ceil(pool.thread_num().max(1));
+        pool.scope(|scope| {
+            for (i, chunk) in self.time.chunks_mut(size * columns_per_task).enumerate() {
+                scope.spawn(async move {
+                    for (c, column) in chunk.chunks_mut(size).enumerate() {
+                        let x = i * columns_per_task + c;
+                        let left = x.saturating_sub(1) * size;
+                        let right = (x + 1).min(size - 1) * size;
+                        for (y, t) in column.iter_mut().enumerate() {
+                            let p = Vec2::new(x as f32 - half, half - 1.0 - y as f32) * 20.0;
+                            let rate = sources.iter().fold(
+                                params.baseline * bubble_factor(bubble, player, p),
+                                |rate, s| rate * s.factor(p),
+                            );
+                            let o = old[x * size + y];
+                            let neighbors = old[left + y]
+                                + old[right + y]
+                                + old[x * size + y.saturating_sub(1)]
+                                + old[x * size + (y + 1).min(size - 1)];
+                            *t = o
+                                + d * rate
+                                + diffusion * (neighbors - 4.0 * o)
+                                + decay * (clock - o);
+                        }
+                    }
+                });
+            }
+        });
+        self.clock += d * params.baseline;
     }
-    fn get(&self, x: u32, y: u32) -> f32 {
-        assert!(x < 128 && y < 128);
-        let i = x * 128 + y;
+
+    pub fn get(&self, x: u32, y: u32) -> f32 {
+        assert!(x < self.size && y < self.size);
+        let i = x * self.size + y;
         self.time[i as usize]
     }
 
-    fn as_tex(&self) -> Image {
+    /// Whether `p` is inside a sink stopping time entirely, the CA leaves these voxels alone like
+    /// the ones around the player.
+    pub fn in_stasis(&self, p: Vec2) -> bool {
+        self.sources
+            .iter()
+            .any(|s| s.rate == 0.0 && s.position.distance_squared(p) < s.radius * s.radius)
+    }
+
+    /// Whether the CA leaves the voxel at `p` alone, around the `player` or in stasis. Both are
+    /// relative to the terrain.
+    pub fn holds(&self, player: Vec2, p: Vec2) -> bool {
+        let protection = self.bubble.protection_radius;
+        player.distance_squared(p) < protection * protection || self.in_stasis(p)
+    }
+
+    /// The smallest and largest local time of any voxel.
+    pub fn range(&self) -> (f32, f32) {
+        self.time
+            .iter()
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &t| {
+                (min.min(t), max.max(t))
+            })
+    }
+
+    pub fn as_tex(&self) -> Image {
         let height_bytes = self.time.iter().flat_map(|f| f.to_le_bytes()).collect();
         let mut i = Image::new(
             Extent3d {
-                width: 128,
-                height: 128,
+                width: self.size,
+                height: self.size,
                 depth_or_array_layers: 1,
             },
             bevy::render::render_resource::TextureDimension::D2,
@@ -706,30 +1175,62 @@ impl TimeDiluationMap {
     }
 }
 
-#[derive(Component, Clone)]
+#[derive(Component, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Killzones {
+    #[serde(with = "grid")]
     voxels: Vec<u128>,
 }
 
 impl Killzones {
-    fn empty() -> Killzones {
+    pub fn empty() -> Killzones {
         Killzones {
             voxels: vec![0; 128],
         }
     }
 
-    fn get(&self, x: u32, y: u32) -> bool {
+    pub fn get(&self, x: u32, y: u32) -> bool {
         assert!(x < 128 && y < 128);
         self.voxels[x as usize] & 1u128 << y > 0
     }
 
-    fn set(&mut self, x: u32, y: u32, v: bool) {
+    pub fn set(&mut self, x: u32, y: u32, v: bool) {
         assert!(x < 128 && y < 128);
         let v = v as u128;
         self.voxels[x as usize] = (self.voxels[x as usize] & !(1 << y)) | (v << y);
     }
 
-    fn collider(&self) -> Option<Collider> {
+    pub fn total(&self) -> u32 {
+        self.voxels.iter().map(|c| c.count_ones()).sum()
+    }
+
+    /// Lava flows into free voxels below and beside it, each with a chance of `chance` per
+    /// generation. Voxels for which `protected` is true stay free.
+    pub fn spread(
+        &mut self,
+        terrain: &VoxelizedView,
+        chance: f32,
+        seed: u64,
+        protected: impl Fn(u32, u32) -> bool,
+    ) {
+        let old = self.clone();
+        for x in 0..128 {
+            for y in 0..128 {
+                if old.get(x, y) || terrain.get(x, y) || protected(x, y) {
+                    continue;
+                }
+                let flows = (y > 0 && old.get(x, y - 1))
+                    || (x > 0 && old.get(x - 1, y))
+                    || (x < 127 && old.get(x + 1, y));
+                let mut state = seed ^ ((x as u64) << 7 | y as u64);
+                let roll = (splitmix64(&mut state) >> 40) as f32 / (1u64 << 24) as f32;
+                if flows && roll < chance {
+                    self.set(x, y, true);
+                }
+            }
+        }
+    }
+
+    pub fn collider(&self) -> Option<Collider> {
         let mut coordinates = Vec::new();
         for x in 0..128 {
             for y in 0..128 {
@@ -748,7 +1249,7 @@ impl Killzones {
         }
     }
 
-    fn as_tex(&self) -> Image {
+    pub fn as_tex(&self) -> Image {
         let mut height_bytes = Vec::new();
         for x in 0..128 {
             for y in 0..128 {
@@ -772,6 +1273,47 @@ impl Killzones {
         i
     }
 }
+
+/// Serializes a 128x128 voxel grid as 128 rows of `#` (set) and `.` (free), like the level image.
+mod grid {
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    pub fn serialize<S: Serializer>(columns: &[u128], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq((0..128).map(|y| {
+            columns
+                .iter()
+                .map(|column| if column & 1 << y > 0 { '#' } else { '.' })
+                .collect::<String>()
+        }))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u128>, D::Error> {
+        let rows = Vec::<String>::deserialize(deserializer)?;
+        if rows.len() != 128 {
+            return Err(D::Error::custom(format!(
+                "{} rows instead of 128",
+                rows.len()
+            )));
+        }
+        let mut columns = vec![0u128; 128];
+        for (y, row) in rows.iter().enumerate() {
+            if row.len() != 128 {
+                return Err(D::Error::custom(format!("row {y} is not 128 voxels wide")));
+            }
+            for (x, voxel) in row.chars().enumerate() {
+                match voxel {
+                    '#' => columns[x] |= 1 << y,
+                    '.' => (),
+                    other => {
+                        return Err(D::Error::custom(format!("unexpected {other:?} in row {y}")));
+                    }
+                }
+            }
+        }
+        Ok(columns)
+    }
+}
+
 // Terrain Shader
 
 #[derive(Asset, TypePath, AsBindGroup, Clone)]
@@ -812,3 +1354,244 @@ impl Material2d for TerrainMaterial {
         bevy::sprite_render::AlphaMode2d::Blend
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::tasks::TaskPoolBuilder;
+
+    use super::*;
+
+    const D: f32 = 1.0 / 60.0;
+    /// Far outside of the level, so the bubble does not touch the field.
+    const AWAY: Vec2 = Vec2::splat(1.0e6);
+
+    fn run(time: &mut TimeDiluationMap, player: Vec2, seconds: f32) {
+        for _ in 0..(seconds / D).round() as u32 {
+            time.advance(player, D);
+        }
+    }
+
+    fn max_step(time: &TimeDiluationMap) -> f32 {
+        let mut step: f32 = 0.0;
+        for x in 0..127 {
+            for y in 0..127 {
+                step = step
+                    .max((time.get(x, y) - time.get(x + 1, y)).abs())
+                    .max((time.get(x, y) - time.get(x, y + 1)).abs());
+            }
+        }
+        step
+    }
+
+    #[test]
+    fn follows_the_baseline_away_from_sources() {
+        let params = TimeFieldParams::for_level(3);
+        let mut time = TimeDiluationMap::with_size(128, params);
+        run(&mut time, AWAY, 2.0);
+        let (min, max) = time.range();
+        assert!((min - 2.0 * params.baseline).abs() < 1e-3, "{min}");
+        assert!((max - 2.0 * params.baseline).abs() < 1e-3, "{max}");
+    }
+
+    #[test]
+    fn the_player_bubble_lags_behind_and_catches_up() {
+        let mut time = TimeDiluationMap::zero();
+        let player = cell_center(UVec2::new(64, 64));
+        run(&mut time, player, 10.0);
+        assert!(time.get(64, 64) < 1.0, "{}", time.get(64, 64));
+        assert!((time.get(0, 0) - 10.0).abs() < 1e-2, "{}", time.get(0, 0));
+
+        let lag = time.clock - time.get(64, 64);
+        run(&mut time, AWAY, 10.0);
+        assert!(time.clock - time.get(64, 64) < lag);
+    }
+
+    #[test]
+    fn the_lag_is_bounded_by_the_decay() {
+        let params = TimeFieldParams::for_level(0);
+        let mut time = TimeDiluationMap::with_size(128, params);
+        run(&mut time, cell_center(UVec2::new(64, 64)), 300.0);
+        let lag = time.clock - time.get(64, 64);
+        assert!(lag < params.baseline / params.decay, "{lag}");
+    }
+
+    #[test]
+    fn no_sharp_edge_at_the_bubble() {
+        let mut time = TimeDiluationMap::zero();
+        run(&mut time, cell_center(UVec2::new(64, 64)), 20.0);
+        // without diffusion the edge of the bubble jumps by the full 20s
+        let step = max_step(&time);
+        assert!(step < 5.0, "{step}");
+    }
+
+    #[test]
+    fn diffusion_conserves_time() {
+        let params = TimeFieldParams {
+            baseline: 1.0,
+            diffusion: 4.0,
+            decay: 0.0,
+        };
+        let mut time = TimeDiluationMap::with_size(128, params);
+        time.time[10 * 128 + 10] = 100.0;
+        run(&mut time, AWAY, 1.0);
+        let excess: f32 = time.time.iter().map(|t| t - time.clock).sum();
+        assert!((excess - 100.0).abs() < 1e-2, "{excess}");
+        assert!(time.get(10, 10) - time.clock < 50.0);
+        assert!(time.get(11, 10) > time.clock);
+        assert!(time.get(10, 9) > time.clock);
+    }
+
+    #[test]
+    fn sources_and_sinks() {
+        let mut time = TimeDiluationMap::zero();
+        time.sources.push(TimeSource {
+            position: cell_center(UVec2::new(20, 20)),
+            radius: 100.0,
+            rate: 3.0,
+        });
+        time.sources.push(TimeSource {
+            position: cell_center(UVec2::new(100, 100)),
+            radius: 100.0,
+            rate: 0.0,
+        });
+        run(&mut time, AWAY, 5.0);
+        assert!(time.get(20, 20) > 7.5, "{}", time.get(20, 20));
+        assert!(time.get(100, 100) < 1.0, "{}", time.get(100, 100));
+        assert!(
+            (time.get(64, 64) - 5.0).abs() < 1e-2,
+            "{}",
+            time.get(64, 64)
+        );
+    }
+
+    #[test]
+    fn deterministic_across_thread_counts() {
+        let player = cell_center(UVec2::new(40, 70));
+        let source = TimeSource {
+            position: cell_center(UVec2::new(90, 30)),
+            radius: 150.0,
+            rate: 2.0,
+        };
+        let mut fields = [1, 3, 8].map(|threads| {
+            let pool = TaskPoolBuilder::new().num_threads(threads).build();
+            let mut time = TimeDiluationMap::for_level(2);
+            time.sources.push(source);
+            for _ in 0..120 {
+                time.advance_on(&pool, player, D);
+            }
+            time.time
+        });
+        let first = std::mem::take(&mut fields[0]);
+        assert!(fields[1..].iter().all(|field| *field == first));
+    }
+
+    /// xorshift, so the grids are the same on every run.
+    fn random_columns(size: usize, seed: u64) -> Vec<u128> {
+        let mut state = seed;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        (0..size * size / 128)
+            .map(|_| ((next() as u128) << 64) | next() as u128)
+            .collect()
+    }
+
+    /// Compares every cell with the per cell lookup the terrain used before the bit planes.
+    fn assert_counts(columns: &[u128], size: usize) {
+        let words = size / 128;
+        let get = |x: i32, y: i32| {
+            let x = x.clamp(0, size as i32 - 1) as usize;
+            let y = y.clamp(0, size as i32 - 1) as usize;
+            columns[x * words + y / 128] & (1 << (y % 128)) > 0
+        };
+        let counts = NeighborCounts::new(columns, words);
+        for x in 0..size as i32 {
+            for y in 0..size as i32 {
+                let mut naive = 0;
+                for x_o in -1..=1 {
+                    for y_o in -1..=1 {
+                        naive += get(x + x_o, y + y_o) as u8;
+                    }
+                }
+                assert_eq!(counts.get(x as u32, y as u32), naive, "{size}² at {x},{y}");
+            }
+        }
+    }
+
+    #[test]
+    fn neighbor_counts_match_the_lookup() {
+        for size in [128, 512] {
+            assert_counts(&random_columns(size, 0x9E37_79B9_7F4A_7C15), size);
+            assert_counts(&random_columns(size, 42), size);
+            // the clamped borders count the edge cells several times
+            assert_counts(&vec![u128::MAX; size * size / 128], size);
+            assert_counts(&vec![0; size * size / 128], size);
+        }
+    }
+
+    fn random_voxels(seed: u64) -> VoxelizedView {
+        VoxelizedView {
+            voxels: random_columns(128, seed),
+            finish_coords: Vec::new(),
+        }
+    }
+
+    fn memory(strength: f32) -> LevelMemory {
+        LevelMemory {
+            authored: random_voxels(7),
+            strength,
+        }
+    }
+
+    #[test]
+    fn no_memory_without_strength() {
+        let voxels = random_voxels(42);
+        let mut time = TimeDiluationMap::for_level(1);
+        run(&mut time, AWAY, MEMORY_RAMP + 1.0);
+        let forgotten = LevelMemory {
+            authored: VoxelizedView::empty(),
+            strength: 0.0,
+        };
+        assert_eq!(
+            evolve(&voxels, &time, &memory(0.0), 1, 3, AWAY),
+            evolve(&voxels, &time, &forgotten, 1, 3, AWAY)
+        );
+    }
+
+    #[test]
+    fn full_strength_restores_the_level_image() {
+        let voxels = random_voxels(42);
+        let mut time = TimeDiluationMap::for_level(1);
+        run(&mut time, AWAY, MEMORY_RAMP + 1.0);
+        let full = memory(1.0);
+        assert_eq!(evolve(&voxels, &time, &full, 1, 3, AWAY), full.authored);
+    }
+
+    #[test]
+    fn no_memory_without_local_time() {
+        let voxels = random_voxels(42);
+        let mut time = TimeDiluationMap::for_level(1);
+        run(&mut time, AWAY, MEMORY_RAMP + 1.0);
+        for y in 0..128 {
+            time.time[20 * 128 + y] = 0.0;
+        }
+        let full = memory(1.0);
+        let pulled = evolve(&voxels, &time, &full, 1, 3, AWAY);
+        let unpulled = evolve(&voxels, &time, &memory(0.0), 1, 3, AWAY);
+        for x in 0..128 {
+            for y in 0..128 {
+                let expected = if x == 20 {
+                    unpulled.get(x, y)
+                } else {
+                    full.authored.get(x, y)
+                };
+                assert_eq!(pulled.get(x, y), expected, "{x},{y}");
+            }
+        }
+        // the column differs from the level image, so the memory had something to pull back
+        assert!((0..128).any(|y| unpulled.get(20, y) != full.authored.get(20, y)));
+    }
+}
@@ -0,0 +1,25 @@
+//! Helpers shared by the tests of several modules.
+
+use std::path::{Path, PathBuf};
+
+/// A directory of its own for a test, removed again when dropped, even if the test failed.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// `name` has to be unique among the tests, they run in parallel.
+    pub fn new(name: &str) -> TempDir {
+        let path = std::env::temp_dir().join(format!("dornburg-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    pub fn join(&self, name: impl AsRef<Path>) -> PathBuf {
+        self.0.join(name)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,266 @@
+use std::{
+    cmp::Ordering,
+    collections::BinaryHeap,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use clap::Args;
+
+use crate::{
+    layout::LevelLayout,
+    player_controller::{JUMP_VELOCITY, MAX_HORIZONTAL_VELOCITY},
+    terrain::{Killzones, UPDATE_INTERVAL, VoxelizedView, cell_center},
+};
+
+use super::{
+    load_level_image,
+    simulation::{PlayerScript, Simulation},
+};
+
+#[derive(Args, Debug, Clone)]
+pub struct AnalyzeArgs {
+    /// The level images to analyze.
+    #[arg(required = true)]
+    levels: Vec<PathBuf>,
+    /// Which level's update rules to use (1-4), defaults to the number in the file name.
+    #[arg(long)]
+    rules: Option<u32>,
+    /// Shifts the noise driving the terrain, like `--seed` of the game.
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+    /// Overrides how strongly the terrain returns to the level image (0-1), see `LevelMemory`.
+    #[arg(long)]
+    memory: Option<f32>,
+    /// Seconds until the poem runs out and the level restarts.
+    #[arg(long, default_value_t = 90.0)]
+    limit: f32,
+}
+
+/// Runs the analysis for every level, returns false if a bone can not be reached in time.
+pub fn analyze(args: &AnalyzeArgs) -> bool {
+    let mut ok = true;
+    for path in &args.levels {
+        match analyze_level(path, args) {
+            Ok(solvable) => ok &= solvable,
+            Err(e) => {
+                eprintln!("{}: error: {e}", path.display());
+                ok = false;
+            }
+        }
+    }
+    ok
+}
+
+fn analyze_level(path: &Path, args: &AnalyzeArgs) -> Result<bool, String> {
+    let image = load_level_image(path)?;
+    let layout = LevelLayout::parse(&image).map_err(|e| e.to_string())?;
+    let level = super::rules_for(path, args.rules)?;
+    let (x, y) = layout.spawn().ok_or("the level has no spawn")?;
+    let spawn = UVec2::new(x, y);
+
+    // The player waits at the spawn, so the terrain around it stays as authored.
+    let script = PlayerScript::Stationary(cell_center(spawn));
+    let generations = (args.limit / UPDATE_INTERVAL) as u32;
+    let mut simulation = Simulation::new(&layout, level, args.seed);
+    if let Some(strength) = args.memory {
+        simulation.memory.strength = strength;
+    }
+    let mut snapshots = vec![simulation.voxels.clone()];
+    while simulation.generation < generations {
+        if simulation.tick(script.position(simulation.elapsed_secs())) {
+            snapshots.push(simulation.voxels.clone());
+        }
+    }
+
+    println!(
+        "{} (rules of level {}, {} generations in {}s)",
+        path.display(),
+        level + 1,
+        snapshots.len(),
+        args.limit
+    );
+    let mut solvable = true;
+    for &(x, y) in &layout.finishes {
+        let bone = UVec2::new(x, y);
+        let dashes: Vec<Option<f32>> = snapshots
+            .iter()
+            .map(|voxels| travel_time(voxels, &layout.killzones, spawn, bone))
+            .collect();
+        let open = dashes.iter().filter(|d| d.is_some()).count();
+        let mut waits: Vec<f32> = Vec::new();
+        let mut stuck = 0;
+        for start in 0..dashes.len() {
+            match first_opening(&dashes, start, args.limit) {
+                Some(generation) => waits.push((generation - start) as f32 * UPDATE_INTERVAL),
+                None => stuck += 1,
+            }
+        }
+        print!(
+            "  bone ({x}, {y}): open from the spawn in {open} of {} generations",
+            dashes.len()
+        );
+        if waits.is_empty() {
+            println!(", never reachable in time");
+            solvable = false;
+            continue;
+        }
+        waits.sort_by(f32::total_cmp);
+        print!(
+            ", wait worst {:.1}s, typical {:.1}s",
+            waits[waits.len() - 1],
+            waits[waits.len() / 2]
+        );
+        if stuck > 0 {
+            print!(
+                ", no opening left when starting after {:.1}s",
+                (dashes.len() - stuck) as f32 * UPDATE_INTERVAL
+            );
+        }
+        println!();
+    }
+
+    let mut position = spawn;
+    let mut now = 0.0;
+    let mut remaining: Vec<UVec2> = layout
+        .finishes
+        .iter()
+        .map(|&(x, y)| UVec2::new(x, y))
+        .collect();
+    while !remaining.is_empty() {
+        let next = remaining
+            .iter()
+            .enumerate()
+            .filter_map(|(i, bone)| {
+                earliest_arrival(
+                    &snapshots,
+                    &layout.killzones,
+                    position,
+                    *bone,
+                    now,
+                    args.limit,
+                )
+                .map(|t| (i, t))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        let Some((i, arrival)) = next else {
+            println!(
+                "  route: stuck at ({}, {}) after {now:.1}s with {} bones left",
+                position.x,
+                position.y,
+                remaining.len()
+            );
+            return Ok(false);
+        };
+        position = remaining.swap_remove(i);
+        now = arrival;
+    }
+    println!("  route: all bones collected after {now:.1}s");
+
+    Ok(solvable)
+}
+
+/// The first generation at or after `start` in which a dash reaches its goal before the limit.
+fn first_opening(dashes: &[Option<f32>], start: usize, limit: f32) -> Option<usize> {
+    (start..dashes.len()).find(|&generation| {
+        dashes[generation].is_some_and(|time| generation as f32 * UPDATE_INTERVAL + time <= limit)
+    })
+}
+
+/// When the player, being at `from` at `now`, can be at `to` at the earliest.
+fn earliest_arrival(
+    snapshots: &[VoxelizedView],
+    killzones: &Killzones,
+    from: UVec2,
+    to: UVec2,
+    now: f32,
+    limit: f32,
+) -> Option<f32> {
+    let current = (now / UPDATE_INTERVAL) as usize;
+    (current..snapshots.len()).find_map(|generation| {
+        let start = now.max(generation as f32 * UPDATE_INTERVAL);
+        travel_time(&snapshots[generation], killzones, from, to)
+            .map(|time| start + time)
+            .filter(|arrival| *arrival <= limit)
+    })
+}
+
+/// Seconds the player needs to get from `from` to `to` if the terrain does not change on the way.
+///
+/// Jumping only sets the vertical velocity and can be repeated in the air,
+/// so the player can climb at jump velocity and move sideways at the maximum horizontal velocity.
+fn travel_time(
+    voxels: &VoxelizedView,
+    killzones: &Killzones,
+    from: UVec2,
+    to: UVec2,
+) -> Option<f32> {
+    let horizontal = 20.0 / MAX_HORIZONTAL_VELOCITY;
+    let vertical = 20.0 / JUMP_VELOCITY;
+    let free = |x: u32, y: u32| !voxels.get(x, y) && !killzones.get(x, y);
+
+    let mut costs = vec![f32::INFINITY; 128 * 128];
+    let mut queue = BinaryHeap::new();
+    costs[(from.x * 128 + from.y) as usize] = 0.0;
+    queue.push(Visit {
+        cost: 0.0,
+        cell: from,
+    });
+    while let Some(Visit { cost, cell }) = queue.pop() {
+        if cell == to {
+            return Some(cost);
+        }
+        if cost > costs[(cell.x * 128 + cell.y) as usize] {
+            continue;
+        }
+        let neighbours = [
+            (cell.x.checked_sub(1), Some(cell.y), horizontal),
+            (Some(cell.x + 1), Some(cell.y), horizontal),
+            (Some(cell.x), cell.y.checked_sub(1), vertical),
+            (Some(cell.x), Some(cell.y + 1), vertical),
+        ];
+        for (x, y, step) in neighbours {
+            let (Some(x), Some(y)) = (x, y) else {
+                continue;
+            };
+            if x >= 128 || y >= 128 || !free(x, y) {
+                continue;
+            }
+            let i = (x * 128 + y) as usize;
+            if cost + step < costs[i] {
+                costs[i] = cost + step;
+                queue.push(Visit {
+                    cost: cost + step,
+                    cell: UVec2::new(x, y),
+                });
+            }
+        }
+    }
+    None
+}
+
+struct Visit {
+    cost: f32,
+    cell: UVec2,
+}
+
+impl PartialEq for Visit {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Visit {}
+
+impl PartialOrd for Visit {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Visit {
+    /// Reversed, so the `BinaryHeap` pops the cheapest visit first.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
@@ -0,0 +1,212 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use bevy::prelude::*;
+use clap::Args;
+use image::{Rgba, RgbaImage};
+
+use crate::{
+    death::DeathCause,
+    telemetry::{Attempt, Outcome, read_log},
+    terrain::world_to_voxel,
+};
+
+use super::load_level_image;
+
+const PATH_COLOR: &str = "#3B5DC9";
+const LAVA_COLOR: &str = "#EF7D57";
+const OUT_OF_BOUNDS_COLOR: &str = "#5D275D";
+const TIME_UP_COLOR: &str = "#38B764";
+const CRUSHED_COLOR: &str = "#333C57";
+
+#[derive(Args, Debug, Clone)]
+pub struct HeatmapArgs {
+    /// Telemetry logs written by the game, see `--telemetry`.
+    #[arg(required = true)]
+    logs: Vec<PathBuf>,
+    /// Directory with the level images, `level_1.png` to `level_4.png`.
+    #[arg(long, default_value = "assets/levels")]
+    levels: PathBuf,
+    /// Directory to write one `level_<n>.png` per level into.
+    #[arg(long, default_value = "heatmaps")]
+    out: PathBuf,
+    /// Size of a voxel in output pixels.
+    #[arg(long, default_value_t = 4)]
+    scale: u32,
+}
+
+/// Draws where the logged attempts went (blue) and died (orange: lava, purple: out of bounds,
+/// green: time up, slate: crushed) over each level image and prints a summary per level.
+/// Both are shaded by how often they happened.
+pub fn heatmap(args: &HeatmapArgs) -> Result<(), String> {
+    let mut levels: BTreeMap<u32, Vec<Attempt>> = BTreeMap::new();
+    for log in &args.logs {
+        for attempt in read_log(log)? {
+            levels.entry(attempt.level).or_default().push(attempt);
+        }
+    }
+    if levels.is_empty() {
+        return Err("the logs hold no attempts".to_string());
+    }
+    std::fs::create_dir_all(&args.out).map_err(|e| format!("{}: {e}", args.out.display()))?;
+
+    for (level, attempts) in &levels {
+        let name = format!("level_{}.png", level + 1);
+        let image = load_level_image(&args.levels.join(&name))
+            .map_err(|e| format!("{}: {e}", args.levels.join(&name).display()))?;
+        let path = args.out.join(&name);
+        draw(&image, attempts, args.scale)
+            .save(&path)
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+        summarize(*level, attempts);
+        println!("  written to {}", path.display());
+    }
+    Ok(())
+}
+
+/// The voxel a position is in, positions outside the level are moved to its edge.
+fn clamped_voxel(p: Vec2) -> UVec2 {
+    let edge = 64.0 * 20.0 - 1.0;
+    world_to_voxel(p.clamp(Vec2::splat(-edge), Vec2::splat(edge))).expect("clamped into the level")
+}
+
+/// How many attempts died of each cause in each voxel.
+fn count_deaths(attempts: &[Attempt]) -> BTreeMap<(u32, u32), BTreeMap<DeathCause, u32>> {
+    let mut deaths: BTreeMap<_, BTreeMap<_, _>> = BTreeMap::new();
+    for attempt in attempts {
+        if let Outcome::Died { cause, position } = attempt.outcome {
+            let cell = clamped_voxel(Vec2::from(position));
+            *deaths
+                .entry((cell.x, cell.y))
+                .or_default()
+                .entry(cause)
+                .or_default() += 1;
+        }
+    }
+    deaths
+}
+
+fn draw(level: &Image, attempts: &[Attempt], scale: u32) -> RgbaImage {
+    let mut visits = [[0u32; 128]; 128];
+    for attempt in attempts {
+        for &p in &attempt.path {
+            let cell = clamped_voxel(Vec2::from(p));
+            visits[cell.x as usize][cell.y as usize] += 1;
+        }
+    }
+    let most = visits.iter().flatten().copied().max().unwrap_or(0).max(1);
+    let path = Srgba::hex(PATH_COLOR).unwrap();
+    let deaths = count_deaths(attempts);
+    let most_deaths = deaths
+        .values()
+        .map(|causes| causes.values().sum::<u32>())
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    let mut image = RgbaImage::new(128 * scale, 128 * scale);
+    for x in 0..128 {
+        for y in 0..128 {
+            let base = level
+                .get_color_at(x, y)
+                .map_or(Srgba::WHITE, |color| color.to_srgba());
+            // empty voxels are transparent, the level is faded so the paths stand out
+            let mut color = Srgba::WHITE.mix(&base.with_alpha(1.0), base.alpha * 0.4);
+            let n = visits[x as usize][y as usize];
+            if n > 0 {
+                let heat = (n as f32 / most as f32).sqrt();
+                color = color.mix(&path, 0.2 + 0.7 * heat);
+            }
+            if let Some(causes) = deaths.get(&(x, y)) {
+                // colored by the most frequent cause, a single death is still clearly visible
+                let (cause, _) = causes.iter().max_by_key(|(_, n)| **n).unwrap();
+                let death = Srgba::hex(match cause {
+                    DeathCause::Lava => LAVA_COLOR,
+                    DeathCause::OutOfBounds => OUT_OF_BOUNDS_COLOR,
+                    DeathCause::TimeUp => TIME_UP_COLOR,
+                    DeathCause::Crushed => CRUSHED_COLOR,
+                })
+                .unwrap();
+                let heat = (causes.values().sum::<u32>() as f32 / most_deaths as f32).sqrt();
+                color = color.mix(&death, 0.5 + 0.5 * heat);
+            }
+            let color = Rgba(color.with_alpha(1.0).to_u8_array());
+            for o_x in 0..scale {
+                for o_y in 0..scale {
+                    image.put_pixel(x * scale + o_x, y * scale + o_y, color);
+                }
+            }
+        }
+    }
+    image
+}
+
+fn summarize(level: u32, attempts: &[Attempt]) {
+    let finished = attempts
+        .iter()
+        .filter(|attempt| attempt.outcome == Outcome::Finished)
+        .count();
+    let mut deaths: BTreeMap<DeathCause, u32> = BTreeMap::new();
+    for attempt in attempts {
+        if let Outcome::Died { cause, .. } = attempt.outcome {
+            *deaths.entry(cause).or_default() += 1;
+        }
+    }
+    println!("level {}", level + 1);
+    println!("  attempts:     {:>5}", attempts.len());
+    println!("  finished:     {finished:>5}");
+    for (cause, count) in &deaths {
+        println!("  {:<13} {count:>5}", format!("{cause:?}:"));
+    }
+    // the n-th bone of every attempt that got that far
+    let most_bones = attempts.iter().map(|a| a.bones.len()).max().unwrap_or(0);
+    for bone in 0..most_bones {
+        let times: Vec<f32> = attempts
+            .iter()
+            .filter_map(|a| a.bones.get(bone))
+            .copied()
+            .collect();
+        println!(
+            "  bone {} after {:.1}s on average, in {} attempts",
+            bone + 1,
+            times.iter().sum::<f32>() / times.len() as f32,
+            times.len()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::difficulty::Difficulty;
+
+    use super::*;
+
+    fn died(cause: DeathCause, position: [f32; 2]) -> Attempt {
+        Attempt {
+            level: 0,
+            difficulty: Difficulty::Wanderer,
+            seed: 0,
+            seconds: 3.0,
+            outcome: Outcome::Died { cause, position },
+            bones: vec![],
+            path: vec![],
+        }
+    }
+
+    #[test]
+    fn counts_every_death_in_a_cell() {
+        let attempts = [
+            died(DeathCause::Lava, [-110.0, 41.0]),
+            died(DeathCause::Lava, [-115.0, 45.0]),
+            died(DeathCause::Crushed, [-112.0, 50.0]),
+            Attempt {
+                outcome: Outcome::Finished,
+                ..died(DeathCause::Lava, [-120.0, 40.0])
+            },
+        ];
+        let deaths = count_deaths(&attempts);
+        assert_eq!(deaths.len(), 1);
+        let causes = deaths.values().next().unwrap();
+        assert_eq!(causes.get(&DeathCause::Lava), Some(&2));
+        assert_eq!(causes.get(&DeathCause::Crushed), Some(&1));
+    }
+}
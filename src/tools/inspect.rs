@@ -0,0 +1,92 @@
+use std::path::Path;
+
+use crate::{
+    layout::LevelLayout,
+    snapshot::LevelSnapshot,
+    terrain::{GROW_THRESHOLD, SHRINK_THRESHOLD},
+};
+
+use super::load_level_image;
+
+/// Prints how many cells of each type the level has and how the terrain will evolve initially.
+///
+/// Anything but a PNG is read as a [`LevelSnapshot`].
+pub fn inspect(path: &Path) -> bool {
+    if path.extension().is_none_or(|extension| extension != "png") {
+        return inspect_snapshot(path);
+    }
+    let image = match load_level_image(path) {
+        Ok(image) => image,
+        Err(e) => {
+            eprintln!("{}: error: {e}", path.display());
+            return false;
+        }
+    };
+    let layout = match LevelLayout::parse(&image) {
+        Ok(layout) => layout,
+        Err(problem) => {
+            eprintln!("{}: error: {problem}", path.display());
+            return false;
+        }
+    };
+
+    let terrain = layout.voxels.total();
+    let kill = layout.killzones.total();
+    let finishes = layout.finishes.len() as u32;
+    let spawns = layout.spawns.len() as u32;
+    let anchors = layout.anchors.len() as u32;
+    let unknown = layout.unknown.len() as u32;
+    let empty = 128 * 128 - terrain - kill - finishes - spawns - anchors - unknown;
+
+    println!("{}", path.display());
+    println!("  terrain:  {terrain:>5}");
+    println!("  killzone: {kill:>5}");
+    println!("  bones:    {finishes:>5}");
+    println!("  spawn:    {spawns:>5}");
+    println!("  lantern:  {anchors:>5}");
+    println!("  unknown:  {unknown:>5}");
+    println!("  empty:    {empty:>5}");
+
+    let regime = if terrain < GROW_THRESHOLD {
+        "grows"
+    } else if terrain > SHRINK_THRESHOLD {
+        "shrinks"
+    } else {
+        "is stable"
+    };
+    println!(
+        "initial total: {terrain} ({:.1}% of grow threshold {GROW_THRESHOLD}, {:.1}% of shrink threshold {SHRINK_THRESHOLD}), the terrain {regime}",
+        terrain as f32 / GROW_THRESHOLD as f32 * 100.0,
+        terrain as f32 / SHRINK_THRESHOLD as f32 * 100.0,
+    );
+
+    let mut ok = true;
+    for problem in layout.problems(&image) {
+        ok &= !problem.is_error();
+        eprintln!("{}: {problem}", problem.severity());
+    }
+    ok
+}
+
+/// Prints where a level in progress is at.
+fn inspect_snapshot(path: &Path) -> bool {
+    let snapshot = match LevelSnapshot::load(path) {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            eprintln!("error: {e}");
+            return false;
+        }
+    };
+    let timer = &snapshot.update_timer.0;
+    println!("{}", path.display());
+    println!("  level:    {:>5}", snapshot.level + 1);
+    println!("  seed:     {:>5}", snapshot.seed);
+    println!("  terrain:  {:>5}", snapshot.voxels.total());
+    println!("  killzone: {:>5}", snapshot.killzones.total());
+    println!("  bones:    {:>5}", snapshot.finishes.len());
+    println!(
+        "next generation in {:.2}s",
+        (timer.duration() - timer.elapsed()).as_secs_f32()
+    );
+    true
+}
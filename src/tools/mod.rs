@@ -0,0 +1,78 @@
+use std::path::{Path, PathBuf};
+
+use crate::layout::load_level_image;
+
+use bevy::{app::AppExit, math::UVec2};
+use clap::Subcommand;
+
+mod analyze;
+mod heatmap;
+mod inspect;
+mod render;
+mod simulation;
+mod validate;
+
+/// Subcommands that run without opening a window.
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Check level images for palette, spawn and bone problems.
+    Validate {
+        #[arg(required = true)]
+        levels: Vec<PathBuf>,
+    },
+    /// Print the cell counts and the initial terrain total of a level image or level snapshot.
+    Inspect { level: PathBuf },
+    /// Evolve the terrain of a level and write every generation as PNG frames or a GIF.
+    Render(render::RenderArgs),
+    /// Check whether every bone can be reached from the spawn while the terrain evolves.
+    Analyze(analyze::AnalyzeArgs),
+    /// Aggregate telemetry logs into a heatmap of paths and deaths over each level image.
+    Heatmap(heatmap::HeatmapArgs),
+}
+
+pub fn run(command: Command) -> AppExit {
+    let ok = match command {
+        Command::Validate { levels } => validate::validate(&levels),
+        Command::Inspect { level } => inspect::inspect(&level),
+        Command::Render(args) => render::render(&args)
+            .inspect_err(|e| eprintln!("error: {e}"))
+            .is_ok(),
+        Command::Analyze(args) => analyze::analyze(&args),
+        Command::Heatmap(args) => heatmap::heatmap(&args)
+            .inspect_err(|e| eprintln!("error: {e}"))
+            .is_ok(),
+    };
+    if ok {
+        AppExit::Success
+    } else {
+        AppExit::error()
+    }
+}
+
+/// Parses a voxel coordinate written as `x,y`.
+fn parse_cell(s: &str) -> Result<UVec2, String> {
+    let (x, y) = s.split_once(',').ok_or("expected `x,y`")?;
+    let x: u32 = x.trim().parse().map_err(|e| format!("{x}: {e}"))?;
+    let y: u32 = y.trim().parse().map_err(|e| format!("{y}: {e}"))?;
+    if x >= 128 || y >= 128 {
+        return Err("cells must be below 128".to_string());
+    }
+    Ok(UVec2::new(x, y))
+}
+
+/// The index of the update rules to use, either given (1-4) or the number at the end of the file name.
+fn rules_for(path: &Path, rules: Option<u32>) -> Result<u32, String> {
+    let rules = rules.or_else(|| {
+        let stem = path.file_stem()?.to_str()?;
+        let digits = stem.len() - stem.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+        stem[stem.len() - digits..].parse().ok()
+    });
+    match rules {
+        Some(rules @ 1..=4) => Ok(rules - 1),
+        Some(rules) => Err(format!("there are no update rules for level {rules}")),
+        None => Err(format!(
+            "{}: can not tell the level from the file name, pass --rules",
+            path.display()
+        )),
+    }
+}
@@ -0,0 +1,151 @@
+use std::{fs::File, path::PathBuf};
+
+use bevy::prelude::*;
+use clap::Args;
+use image::{
+    Delay, Frame, Rgba, RgbaImage,
+    codecs::gif::{GifEncoder, Repeat},
+};
+
+use crate::{
+    layout::{FINISH_COLOR, KILL_COLOR, LevelLayout, SPAWN_COLOR, TERRAIN_COLOR},
+    terrain::{voxel_to_world, world_to_voxel},
+};
+
+use super::{
+    load_level_image,
+    simulation::{PlayerScript, Simulation},
+};
+
+const EMPTY_COLOR: &str = "#F4F4F4";
+const PROTECTED_COLOR: &str = "#94B0C2";
+
+#[derive(Args, Debug, Clone)]
+pub struct RenderArgs {
+    /// The level image to evolve.
+    level: PathBuf,
+    /// How many terrain generations to simulate.
+    #[arg(long, default_value_t = 40)]
+    generations: u32,
+    /// Which level's update rules to use (1-4), defaults to the number in the file name.
+    #[arg(long)]
+    rules: Option<u32>,
+    /// Shifts the noise driving the terrain, like `--seed` of the game.
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+    /// Overrides how strongly the terrain returns to the level image (0-1), see `LevelMemory`.
+    #[arg(long)]
+    memory: Option<f32>,
+    /// Stationary player cell as `x,y`, defaults to the spawn.
+    #[arg(long, value_parser = super::parse_cell)]
+    player: Option<UVec2>,
+    /// File with `seconds x y` lines, the player moves linearly between these cells.
+    #[arg(long, conflicts_with = "player")]
+    script: Option<PathBuf>,
+    /// Directory to write one PNG per generation into.
+    #[arg(long)]
+    frames: Option<PathBuf>,
+    /// Animated GIF to write all generations into.
+    #[arg(long)]
+    gif: Option<PathBuf>,
+    /// Size of a voxel in output pixels.
+    #[arg(long, default_value_t = 4)]
+    scale: u32,
+    /// Time between GIF frames.
+    #[arg(long, default_value_t = 200)]
+    delay_ms: u32,
+}
+
+pub fn render(args: &RenderArgs) -> Result<(), String> {
+    if args.frames.is_none() && args.gif.is_none() {
+        return Err("nothing to write, pass --frames and/or --gif".to_string());
+    }
+    let image = load_level_image(&args.level)?;
+    let layout = LevelLayout::parse(&image).map_err(|e| e.to_string())?;
+    let level = super::rules_for(&args.level, args.rules)?;
+    let script = PlayerScript::from_args(&layout, args.player, args.script.as_deref())?;
+
+    if let Some(dir) = &args.frames {
+        std::fs::create_dir_all(dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+    }
+
+    let mut simulation = Simulation::new(&layout, level, args.seed);
+    if let Some(strength) = args.memory {
+        simulation.memory.strength = strength;
+    }
+    let mut frames = Vec::new();
+    let mut player = script.position(0.0);
+    frames.push(draw(&simulation, &layout, player, args.scale));
+    while simulation.generation < args.generations {
+        player = script.position(simulation.elapsed_secs());
+        if simulation.tick(player) {
+            frames.push(draw(&simulation, &layout, player, args.scale));
+        }
+    }
+
+    if let Some(dir) = &args.frames {
+        for (generation, frame) in frames.iter().enumerate() {
+            let path = dir.join(format!("generation_{generation:04}.png"));
+            frame
+                .save(&path)
+                .map_err(|e| format!("{}: {e}", path.display()))?;
+        }
+        println!("wrote {} frames to {}", frames.len(), dir.display());
+    }
+    if let Some(path) = &args.gif {
+        let file = File::create(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        let mut encoder = GifEncoder::new(file);
+        encoder
+            .set_repeat(Repeat::Infinite)
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+        let delay = Delay::from_numer_denom_ms(args.delay_ms, 1);
+        encoder
+            .encode_frames(
+                frames
+                    .iter()
+                    .map(|frame| Frame::from_parts(frame.clone(), 0, 0, delay)),
+            )
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+        println!("wrote {} generations to {}", frames.len(), path.display());
+    }
+    Ok(())
+}
+
+fn draw(simulation: &Simulation, layout: &LevelLayout, player: Vec2, scale: u32) -> RgbaImage {
+    let terrain = rgba(TERRAIN_COLOR);
+    let kill = rgba(KILL_COLOR);
+    let finish = rgba(FINISH_COLOR);
+    let empty = rgba(EMPTY_COLOR);
+    let protected = rgba(PROTECTED_COLOR);
+    let player_cell = world_to_voxel(player);
+    let protection = simulation.time.bubble.protection_radius;
+
+    let mut image = RgbaImage::new(128 * scale, 128 * scale);
+    for x in 0..128 {
+        for y in 0..128 {
+            let color = if player_cell == Some(UVec2::new(x, y)) {
+                rgba(SPAWN_COLOR)
+            } else if layout.finishes.contains(&(x, y)) {
+                finish
+            } else if layout.killzones.get(x, y) {
+                kill
+            } else if simulation.voxels.get(x, y) {
+                terrain
+            } else if player.distance_squared(voxel_to_world(x, y)) < protection * protection {
+                protected
+            } else {
+                empty
+            };
+            for o_x in 0..scale {
+                for o_y in 0..scale {
+                    image.put_pixel(x * scale + o_x, y * scale + o_y, color);
+                }
+            }
+        }
+    }
+    image
+}
+
+fn rgba(hex: &str) -> Rgba<u8> {
+    Rgba(Srgba::hex(hex).unwrap().to_u8_array())
+}
@@ -0,0 +1,160 @@
+use std::{path::Path, time::Duration};
+
+use bevy::prelude::*;
+
+use crate::{
+    anchor::{ANCHOR_DURATION, ANCHOR_RADIUS},
+    layout::LevelLayout,
+    terrain::{
+        LevelMemory, TimeDiluationMap, TimeSource, UPDATE_INTERVAL, VoxelizedView, cell_center,
+        evolve,
+    },
+};
+
+/// Frame time used when running a level without a window.
+pub const TICK: f32 = 1.0 / 60.0;
+
+/// The CPU side of a running level, stepped the same way `update_time` and `update_terrain` step it.
+pub struct Simulation {
+    pub voxels: VoxelizedView,
+    pub time: TimeDiluationMap,
+    pub memory: LevelMemory,
+    pub level: u32,
+    pub seed: u64,
+    pub generation: u32,
+    /// The lanterns of the level, they stay where they are and run out after [`ANCHOR_DURATION`].
+    anchors: Vec<TimeSource>,
+    ticks: u32,
+    timer: Timer,
+}
+
+impl Simulation {
+    pub fn new(layout: &LevelLayout, level: u32, seed: u64) -> Simulation {
+        Simulation {
+            voxels: layout.voxels.clone(),
+            time: TimeDiluationMap::for_level(level),
+            memory: LevelMemory::for_level(&layout.voxels, level),
+            level,
+            seed,
+            generation: 0,
+            anchors: layout
+                .anchors
+                .iter()
+                .map(|&(x, y)| TimeSource {
+                    position: cell_center(UVec2::new(x, y)),
+                    radius: ANCHOR_RADIUS,
+                    rate: 0.0,
+                })
+                .collect(),
+            ticks: 0,
+            timer: Timer::from_seconds(UPDATE_INTERVAL, TimerMode::Repeating),
+        }
+    }
+
+    /// Advances the level by one frame, returns true if the terrain evolved.
+    pub fn tick(&mut self, player: Vec2) -> bool {
+        if self.elapsed_secs() >= ANCHOR_DURATION {
+            self.time.sources.clear();
+        } else {
+            self.time.sources.clone_from(&self.anchors);
+        }
+        self.time.advance(player, TICK);
+        self.ticks += 1;
+        self.timer.tick(Duration::from_secs_f32(TICK));
+        if self.timer.just_finished() {
+            self.voxels = evolve(
+                &self.voxels,
+                &self.time,
+                &self.memory,
+                self.level,
+                self.seed,
+                player,
+            );
+            self.generation += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn elapsed_secs(&self) -> f32 {
+        self.ticks as f32 * TICK
+    }
+}
+
+/// Where the simulated player is over time, in world coordinates.
+pub enum PlayerScript {
+    Stationary(Vec2),
+    /// `(seconds, position)` pairs sorted by time, the player moves linearly between them.
+    Waypoints(Vec<(f32, Vec2)>),
+}
+
+impl PlayerScript {
+    /// Uses the script file if given, otherwise a stationary player in the given cell or at the spawn.
+    pub fn from_args(
+        layout: &LevelLayout,
+        player: Option<UVec2>,
+        script: Option<&Path>,
+    ) -> Result<PlayerScript, String> {
+        if let Some(path) = script {
+            return PlayerScript::load(path);
+        }
+        let cell = player
+            .or(layout.spawn().map(|(x, y)| UVec2::new(x, y)))
+            .ok_or("the level has no spawn, pass --player")?;
+        Ok(PlayerScript::Stationary(cell_center(cell)))
+    }
+
+    /// Each non empty line not starting with `#` is `seconds x y`, with x and y in cells.
+    fn load(path: &Path) -> Result<PlayerScript, String> {
+        let content =
+            std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        let mut waypoints = Vec::new();
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let parsed = match parts[..] {
+                [t, x, y] => t
+                    .parse::<f32>()
+                    .ok()
+                    .zip(x.parse::<u32>().ok())
+                    .zip(y.parse::<u32>().ok()),
+                _ => None,
+            };
+            let Some(((t, x), y)) = parsed.filter(|((_, x), y)| *x < 128 && *y < 128) else {
+                return Err(format!(
+                    "{}:{}: expected `seconds x y` with cells below 128",
+                    path.display(),
+                    i + 1
+                ));
+            };
+            waypoints.push((t, cell_center(UVec2::new(x, y))));
+        }
+        if waypoints.is_empty() {
+            return Err(format!("{}: no waypoints", path.display()));
+        }
+        waypoints.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Ok(PlayerScript::Waypoints(waypoints))
+    }
+
+    pub fn position(&self, t: f32) -> Vec2 {
+        match self {
+            PlayerScript::Stationary(p) => *p,
+            PlayerScript::Waypoints(waypoints) => {
+                let next = waypoints.partition_point(|(w_t, _)| *w_t <= t);
+                if next == 0 {
+                    waypoints[0].1
+                } else if next == waypoints.len() {
+                    waypoints[next - 1].1
+                } else {
+                    let (t0, p0) = waypoints[next - 1];
+                    let (t1, p1) = waypoints[next];
+                    p0.lerp(p1, (t - t0) / (t1 - t0))
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use std::path::PathBuf;
+
+use crate::layout::LevelLayout;
+
+use super::load_level_image;
+
+/// Prints every problem of every level, returns false if any level has an error.
+pub fn validate(levels: &[PathBuf]) -> bool {
+    let mut ok = true;
+    for path in levels {
+        let image = match load_level_image(path) {
+            Ok(image) => image,
+            Err(e) => {
+                eprintln!("{}: error: {e}", path.display());
+                ok = false;
+                continue;
+            }
+        };
+        let problems = match LevelLayout::parse(&image) {
+            Ok(layout) => layout.problems(&image),
+            Err(problem) => vec![problem],
+        };
+        if problems.is_empty() {
+            println!("{}: ok", path.display());
+        }
+        for problem in problems {
+            ok &= !problem.is_error();
+            eprintln!("{}: {}: {problem}", path.display(), problem.severity());
+        }
+    }
+    ok
+}
@@ -0,0 +1,207 @@
+use bevy::{prelude::*, render::render_resource::AsBindGroup, shader::ShaderRef};
+
+use crate::{levels::LevelScreens, player::PlayerMarker, screens::Screen};
+
+/// Seconds until the screen is covered.
+const OUT_SECONDS: f32 = 0.6;
+/// Seconds the screen stays covered after switching, while the next screen is built.
+const HOLD_SECONDS: f32 = 0.15;
+/// Seconds until the screen is uncovered again.
+const IN_SECONDS: f32 = 0.5;
+
+/// Covers the screen, switches the state behind it and uncovers it again, see [`Transition`].
+pub struct TransitionPlugin;
+
+impl Plugin for TransitionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(UiMaterialPlugin::<TransitionMaterial>::default());
+        app.init_resource::<Transition>();
+        app.add_systems(Startup, spawn_cover);
+        app.add_systems(Update, run_transition);
+    }
+}
+
+/// How the screen is covered, the order is the style index in `transition.wgsl`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransitionStyle {
+    /// Darkness closing in around the player.
+    Vignette,
+    Fade,
+    /// A shrinking circle around the player, or the middle of the screen without one.
+    Iris,
+    /// Lines of ink running across the screen like the poem.
+    Ink,
+}
+
+impl TransitionStyle {
+    fn color(self) -> LinearRgba {
+        match self {
+            TransitionStyle::Ink => Srgba::hex("#1A1C2C").unwrap().into(),
+            _ => LinearRgba::BLACK,
+        }
+    }
+}
+
+/// The state a transition switches to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target {
+    /// Leaving [`Screen::Gameplay`] also ends the level.
+    Screen(Screen),
+    Level(LevelScreens),
+}
+
+impl From<Screen> for Target {
+    fn from(screen: Screen) -> Target {
+        Target::Screen(screen)
+    }
+}
+
+impl From<LevelScreens> for Target {
+    fn from(level: LevelScreens) -> Target {
+        Target::Level(level)
+    }
+}
+
+/// The transition in progress, started with [`Transition::start`] instead of setting
+/// [`NextState`] directly.
+///
+/// The game is frozen (`Time<Virtual>` is paused) while the screen is being covered and while
+/// it stays covered, the animation itself runs on real time.
+#[derive(Resource, Default)]
+pub struct Transition {
+    running: Option<Running>,
+}
+
+struct Running {
+    target: Target,
+    style: TransitionStyle,
+    phase: Phase,
+    elapsed: f32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Out,
+    Hold,
+    In,
+}
+
+impl Transition {
+    /// Switches to `target` once the screen is covered, ignored while another transition runs.
+    pub fn start(&mut self, target: impl Into<Target>, style: TransitionStyle) {
+        if self.running.is_none() {
+            self.running = Some(Running {
+                target: target.into(),
+                style,
+                phase: Phase::Out,
+                elapsed: 0.0,
+            });
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+}
+
+#[derive(Asset, TypePath, AsBindGroup, Clone)]
+pub struct TransitionMaterial {
+    /// x: how far the screen is covered (0-1), y: the style, zw: the player in UV.
+    #[uniform(0)]
+    params: Vec4,
+    #[uniform(1)]
+    color: LinearRgba,
+}
+
+impl UiMaterial for TransitionMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/transition.wgsl".into()
+    }
+}
+
+fn spawn_cover(mut commands: Commands, mut materials: ResMut<Assets<TransitionMaterial>>) {
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            width: percent(100),
+            height: percent(100),
+            ..Default::default()
+        },
+        // above everything but the death overlay
+        GlobalZIndex(100),
+        Pickable::IGNORE,
+        MaterialNode(materials.add(TransitionMaterial {
+            params: Vec4::new(0.0, 0.0, 0.5, 0.5),
+            color: LinearRgba::BLACK,
+        })),
+    ));
+}
+
+fn run_transition(
+    mut transition: ResMut<Transition>,
+    time: Res<Time<Real>>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    mut next_screen: ResMut<NextState<Screen>>,
+    mut next_level: ResMut<NextState<LevelScreens>>,
+    level: Res<State<LevelScreens>>,
+    cover: Single<&MaterialNode<TransitionMaterial>>,
+    mut materials: ResMut<Assets<TransitionMaterial>>,
+    camera: Query<(&Camera, &GlobalTransform)>,
+    player: Query<&GlobalTransform, With<PlayerMarker>>,
+) {
+    let Some(running) = &mut transition.running else {
+        return;
+    };
+    running.elapsed += time.delta_secs();
+    match running.phase {
+        Phase::Out => {
+            virtual_time.pause();
+            if running.elapsed >= OUT_SECONDS {
+                match running.target {
+                    Target::Screen(screen) => {
+                        next_screen.set(screen);
+                        if screen != Screen::Gameplay && *level.get() != LevelScreens::None {
+                            next_level.set(LevelScreens::None);
+                        }
+                    }
+                    Target::Level(target) => next_level.set(target),
+                }
+                running.phase = Phase::Hold;
+                running.elapsed = 0.0;
+            }
+        }
+        Phase::Hold => {
+            if running.elapsed >= HOLD_SECONDS {
+                virtual_time.unpause();
+                running.phase = Phase::In;
+                running.elapsed = 0.0;
+            }
+        }
+        Phase::In => {}
+    }
+    let covered = match running.phase {
+        Phase::Out => running.elapsed / OUT_SECONDS,
+        Phase::Hold => 1.0,
+        Phase::In => 1.0 - running.elapsed / IN_SECONDS,
+    }
+    .clamp(0.0, 1.0);
+    let style = running.style;
+    if running.phase == Phase::In && running.elapsed >= IN_SECONDS {
+        transition.running = None;
+    }
+
+    let center = match (camera.single(), player.single()) {
+        (Ok((camera, camera_transform)), Ok(player)) => camera
+            .world_to_viewport(camera_transform, player.translation())
+            .ok()
+            .zip(camera.logical_viewport_size())
+            .map_or(Vec2::splat(0.5), |(position, size)| position / size),
+        _ => Vec2::splat(0.5),
+    };
+    if let Some(material) = materials.get_mut(&cover.0) {
+        // eased, so the cover settles instead of stopping abruptly
+        let covered = covered * covered * (3.0 - 2.0 * covered);
+        material.params = Vec4::new(covered, style as u32 as f32, center.x, center.y);
+        material.color = style.color();
+    }
+}